@@ -0,0 +1,29 @@
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+use xiuxian::fixture::{Fixture, Stage};
+
+const POPULATIONS: [usize; 3] = [1_000, 10_000, 100_000];
+
+// Each system on fresh copies of the same seeded world, since most of them change it.
+fn tick(c: &mut Criterion) {
+    for stage in Stage::ALL {
+        let mut group = c.benchmark_group(stage.name());
+        group.sample_size(20);
+        for population in POPULATIONS {
+            group.bench_with_input(
+                BenchmarkId::from_parameter(population),
+                &population,
+                |b, &population| {
+                    b.iter_batched(
+                        || Fixture::new(stage, population),
+                        |mut fixture| fixture.run(),
+                        BatchSize::LargeInput,
+                    )
+                },
+            );
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, tick);
+criterion_main!(benches);
@@ -0,0 +1,260 @@
+// Streaming accumulators: constant memory per sample, updated one value at a time and
+// mergeable, so statistics never need the whole population at once.
+
+// Welford's online mean and variance, with the extremes seen.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Welford {
+    count: u64,
+    mean: f64,
+    m2: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Welford {
+    pub fn push(&mut self, x: f64) {
+        if self.count == 0 {
+            (self.min, self.max) = (x, x);
+        } else {
+            self.min = self.min.min(x);
+            self.max = self.max.max(x);
+        }
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    pub fn merge(&mut self, other: &Welford) {
+        if other.count == 0 {
+            return;
+        }
+        if self.count == 0 {
+            *self = *other;
+            return;
+        }
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        self.mean += delta * other.count as f64 / count as f64;
+        self.m2 += other.m2 + delta * delta * (self.count * other.count) as f64 / count as f64;
+        self.count = count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    // Population variance.
+    pub fn variance(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.m2 / self.count as f64
+        }
+    }
+
+    pub fn std(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+}
+
+// Quantiles within 1% relative error, values fall into logarithmic buckets.
+const ALPHA: f64 = 0.01;
+const GAMMA: f64 = (1.0 + ALPHA) / (1.0 - ALPHA);
+// Buckets cover roughly 1e-9 to 1e13, values beyond fall into the outermost ones.
+const MIN_INDEX: i32 = -1024;
+const BUCKETS: usize = 2560;
+
+// Buckets are only held over the range of values seen, which for the quantities of a world
+// spans a few hundred of them.
+#[derive(Debug, Default, Clone)]
+pub struct Sketch {
+    count: u64,
+    // Values not above zero, which have no logarithm.
+    zeros: u64,
+    // Index of the first bucket held.
+    offset: usize,
+    buckets: Vec<u64>,
+}
+
+impl Sketch {
+    fn index(x: f64) -> usize {
+        let i = (x.ln() / GAMMA.ln()).ceil() as i32;
+        (i - MIN_INDEX).clamp(0, BUCKETS as i32 - 1) as usize
+    }
+
+    fn value(index: usize) -> f64 {
+        2.0 * GAMMA.powi(index as i32 + MIN_INDEX) / (GAMMA + 1.0)
+    }
+
+    // Extends the buckets held to cover `first..=last`.
+    fn cover(&mut self, first: usize, last: usize) {
+        if self.buckets.is_empty() {
+            self.offset = first;
+        }
+        if first < self.offset {
+            let extra = self.offset - first;
+            self.buckets.splice(0..0, std::iter::repeat_n(0, extra));
+            self.offset = first;
+        }
+        if last >= self.offset + self.buckets.len() {
+            self.buckets.resize(last - self.offset + 1, 0);
+        }
+    }
+
+    pub fn push(&mut self, x: f64) {
+        self.count += 1;
+        if x > 0.0 {
+            let i = Self::index(x);
+            self.cover(i, i);
+            self.buckets[i - self.offset] += 1;
+        } else {
+            self.zeros += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: &Sketch) {
+        self.count += other.count;
+        self.zeros += other.zeros;
+        if other.buckets.is_empty() {
+            return;
+        }
+        self.cover(other.offset, other.offset + other.buckets.len() - 1);
+        let start = other.offset - self.offset;
+        for (a, b) in self.buckets[start..].iter_mut().zip(&other.buckets) {
+            *a += b;
+        }
+    }
+
+    // Empties the sketch, keeping the buckets it holds for the next pass.
+    pub fn clear(&mut self) {
+        self.count = 0;
+        self.zeros = 0;
+        self.buckets.fill(0);
+    }
+
+    // Representative values of the non-empty buckets in ascending order, with their counts.
+    fn groups(&self) -> impl Iterator<Item = (f64, u64)> + '_ {
+        let zeros = (self.zeros > 0).then_some((0.0, self.zeros));
+        let buckets = self.buckets.iter().enumerate();
+        zeros.into_iter().chain(
+            buckets
+                .filter(|(_, c)| **c > 0)
+                .map(|(i, c)| (Self::value(self.offset + i), *c)),
+        )
+    }
+
+    pub fn quantile(&self, q: f64) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let rank = (q * (self.count - 1) as f64) as u64;
+        let mut seen = 0;
+        for (value, count) in self.groups() {
+            seen += count;
+            if rank < seen {
+                return value;
+            }
+        }
+        0.0
+    }
+
+    // Gini coefficient over the bucketed values, each group taking consecutive ranks.
+    pub fn gini(&self) -> f64 {
+        let n = self.count as f64;
+        let (mut total, mut weighted, mut below) = (0.0, 0.0, 0.0);
+        for (value, count) in self.groups() {
+            let c = count as f64;
+            total += value * c;
+            weighted += value * c * (2.0 * below + c - n);
+            below += c;
+        }
+        if total == 0.0 {
+            0.0
+        } else {
+            weighted / (n * total)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_welford() {
+        let mut a = Welford::default();
+        let mut b = Welford::default();
+        for x in [2.0, 4.0, 4.0, 4.0] {
+            a.push(x);
+        }
+        for x in [5.0, 5.0, 7.0, 9.0] {
+            b.push(x);
+        }
+        a.merge(&b);
+        assert_eq!(a.count(), 8);
+        assert_eq!(a.mean(), 5.0);
+        assert_eq!(a.std(), 2.0);
+        assert_eq!((a.min(), a.max()), (2.0, 9.0));
+    }
+
+    #[test]
+    fn test_sketch() {
+        let mut a = Sketch::default();
+        let mut b = Sketch::default();
+        for x in 1..=500 {
+            a.push(x as f64);
+        }
+        for x in 501..=1000 {
+            b.push(x as f64);
+        }
+        a.merge(&b);
+        for (q, exact) in [(0.1, 100.0), (0.5, 500.0), (0.9, 900.0)] {
+            assert!((a.quantile(q) - exact).abs() / exact < 2.0 * ALPHA);
+        }
+
+        let mut equal = Sketch::default();
+        let mut skewed = Sketch::default();
+        for x in [5.0, 5.0, 5.0, 5.0] {
+            equal.push(x);
+        }
+        for x in [0.0, 0.0, 0.0, 8.0] {
+            skewed.push(x);
+        }
+        assert_eq!(equal.gini(), 0.0);
+        assert!((skewed.gini() - 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_sketch_range() {
+        let mut high = Sketch::default();
+        let mut low = Sketch::default();
+        for x in 1000..2000 {
+            high.push(x as f64);
+        }
+        for x in 1..=10 {
+            low.push(x as f64);
+        }
+        assert!(high.buckets.len() < 50);
+        high.merge(&low);
+        assert!(high.buckets.len() < 500);
+        assert!((high.quantile(0.0) - 1.0).abs() < 2.0 * ALPHA);
+        assert!((high.quantile(1.0) - 1999.0).abs() / 1999.0 < 2.0 * ALPHA);
+        high.clear();
+        assert_eq!(high.quantile(0.5), 0.0);
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_prng::WyRand;
+use bevy_rand::global::GlobalEntropy;
+use itertools::Itertools;
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::battle::{self, Available, BattleEvent, BattlePair, BattleQuery, BattleQueryReadOnly};
+use crate::config::Config;
+use crate::demonic::absorb;
+use crate::system::GamePlay;
+use crate::vein;
+
+// A group of attackers ganging up on a single target.
+#[derive(Debug)]
+pub struct Ambush {
+    pub target: Entity,
+    pub attackers: Vec<Entity>,
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct Ambushes(Vec<Ambush>);
+
+#[derive(Event)]
+pub struct AmbushEvent {
+    pub target: Entity,
+    pub attackers: usize,
+    pub success: bool,
+}
+
+// Chance of a group with total cultivation `power` to overwhelm the target.
+fn group_win_rate(power: u64, target: u64) -> f64 {
+    power as f64 / (power + target) as f64
+}
+
+fn plot_ambushes(
+    mut rng: GlobalEntropy<WyRand>,
+    config: Res<Config>,
+    data: Query<BattleQueryReadOnly, Available>,
+    mut pairs: ResMut<BattlePair>,
+    mut ambushes: ResMut<Ambushes>,
+) {
+    ambushes.0.clear();
+    if !config.ambush.enabled {
+        return;
+    }
+    let rate = config.time.rate(config.ambush.rate);
+    let locals = data
+        .iter()
+        .map(|i| (i.position.copied(), i.entity))
+        .into_group_map();
+    let mut busy = HashSet::new();
+    for plotter in data.iter() {
+        if busy.contains(&plotter.entity) || rng.random::<f64>() >= rate {
+            continue;
+        }
+        let cell = &locals[&plotter.position.copied()];
+        // The strongest peer around is the one worth ganging up on, sect mates never fight.
+        let Some(target) = cell
+            .iter()
+            .filter(|e| !busy.contains(*e))
+            .map(|e| data.get(*e).unwrap())
+            .filter(|i| i.cultivation.level == plotter.cultivation.level)
+            .filter(|i| plotter.sect.is_none() || i.sect != plotter.sect)
+            .max_by_key(|i| i.cultivation.cultivation)
+        else {
+            continue;
+        };
+        let strength = target.cultivation.cultivation;
+        if strength <= plotter.cultivation.cultivation {
+            continue;
+        }
+        let mut attackers = vec![plotter.entity];
+        let mut power = plotter.cultivation.cultivation;
+        let mut candidates = cell.clone();
+        candidates.shuffle(&mut rng);
+        for candidate in candidates {
+            if attackers.len() >= config.ambush.max_size {
+                break;
+            }
+            if busy.contains(&candidate) || attackers.contains(&candidate) {
+                continue;
+            }
+            // Allies are peers of the target outside its sect, as in any other fight.
+            let ally = data.get(candidate).unwrap();
+            if ally.entity == target.entity
+                || ally.cultivation.level != target.cultivation.level
+                || (ally.sect.is_some() && ally.sect == target.sect)
+            {
+                continue;
+            }
+            // Allies join only if the group with them looks worth the risk.
+            let joined = power + ally.cultivation.cultivation;
+            if ally.battle.courage > 1.0 - group_win_rate(joined, strength) {
+                attackers.push(candidate);
+                power = joined;
+            }
+        }
+        if attackers.len() < 2 || plotter.battle.courage <= 1.0 - group_win_rate(power, strength) {
+            continue;
+        }
+        busy.extend(attackers.iter().copied());
+        busy.insert(target.entity);
+        pairs.withdraw(&attackers);
+        pairs.withdraw(&[target.entity]);
+        ambushes.0.push(Ambush {
+            target: target.entity,
+            attackers,
+        });
+    }
+}
+
+// Every attacker may fall to the target, the ambush succeeds if the group wins and someone
+// is left standing, who share the loot.
+fn ambush(
+    mut rng: GlobalEntropy<WyRand>,
+    config: Res<Config>,
+    ambushes: Res<Ambushes>,
+    mut data: Query<BattleQuery>,
+    mut ev_battle: EventWriter<BattleEvent>,
+    mut ev_ambush: EventWriter<AmbushEvent>,
+) {
+    for ambush in &ambushes.0 {
+        let strength = data.get(ambush.target).unwrap().cultivation.cultivation;
+        let power = ambush
+            .attackers
+            .iter()
+            .map(|e| data.get(*e).unwrap().cultivation.cultivation)
+            .sum();
+        let win_rate = group_win_rate(power, strength);
+        let (fallen, survivors): (Vec<Entity>, Vec<Entity>) = ambush
+            .attackers
+            .iter()
+            .partition(|_| rng.random::<f64>() >= win_rate);
+        let success = !survivors.is_empty() && rng.random::<f64>() < win_rate;
+        for attacker in &fallen {
+            let mut loser = data.get_mut(*attacker).unwrap();
+            loser.life.alive = false;
+            let fallen = loser.cultivation.cultivation;
+            let mut target = data.get_mut(ambush.target).unwrap();
+            let demonic = target.demonic.is_some();
+            target.gain(absorb(&config.demonic, demonic, fallen));
+            ev_battle.write(BattleEvent {
+                winner: ambush.target,
+                loser: *attacker,
+                killed: true,
+            });
+        }
+        if success {
+            let mut target = data.get_mut(ambush.target).unwrap();
+            target.life.alive = false;
+            let (loot, shares) = (target.cultivation.cultivation, survivors.len() as u64);
+            for survivor in &survivors {
+                let mut survivor = data.get_mut(*survivor).unwrap();
+                let demonic = survivor.demonic.is_some();
+                survivor.gain(absorb(&config.demonic, demonic, loot) / shares);
+            }
+            ev_battle.write(BattleEvent {
+                winner: survivors[0],
+                loser: ambush.target,
+                killed: true,
+            });
+        }
+        ev_ambush.write(AmbushEvent {
+            target: ambush.target,
+            attackers: ambush.attackers.len(),
+            success,
+        });
+    }
+}
+
+pub fn ambush_plugin(app: &mut App) {
+    app.init_resource::<Ambushes>();
+    app.add_event::<AmbushEvent>();
+    app.add_systems(
+        Update,
+        (
+            plot_ambushes
+                .after(vein::challenge_veins)
+                .in_set(GamePlay::FilterPair),
+            ambush.before(battle::battle).in_set(GamePlay::Battle),
+        ),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battle::Courage;
+    use crate::cultivation::Cultivation;
+    use crate::level::Level;
+    use crate::life::Life;
+    use crate::sect::SectMember;
+    use bevy_rand::plugin::EntropyPlugin;
+
+    #[test]
+    fn test_plot_ambushes() {
+        let mut app = App::new();
+        app.add_plugins(EntropyPlugin::<WyRand>::with_seed(42u64.to_le_bytes()));
+        let mut config = Config::default();
+        config.ambush.enabled = true;
+        config.ambush.rate = 1.0;
+        app.insert_resource(config);
+        app.init_resource::<BattlePair>();
+        app.init_resource::<Ambushes>();
+        app.add_systems(Update, plot_ambushes);
+
+        let life = Life {
+            age: 20,
+            lifespan: 100,
+            alive: true,
+            ticks: 0,
+        };
+        let mut spawn = |cultivation| {
+            app.world_mut()
+                .spawn((
+                    Cultivation {
+                        level: Level::Foundation,
+                        cultivation,
+                    },
+                    Courage { courage: 0.9 },
+                    life.clone(),
+                ))
+                .id()
+        };
+        let outlier = spawn(1000);
+        let weak = [spawn(100), spawn(100), spawn(100), spawn(100)];
+        app.update();
+
+        let ambushes = &app.world().resource::<Ambushes>().0;
+        assert_eq!(ambushes.len(), 1);
+        assert_eq!(ambushes[0].target, outlier);
+        assert_eq!(ambushes[0].attackers.len(), 3);
+        assert!(ambushes[0].attackers.iter().all(|a| weak.contains(a)));
+    }
+
+    #[test]
+    fn test_spare_sect_mate() {
+        let mut app = App::new();
+        app.add_plugins(EntropyPlugin::<WyRand>::with_seed(42u64.to_le_bytes()));
+        let mut config = Config::default();
+        config.ambush.enabled = true;
+        config.ambush.rate = 1.0;
+        app.insert_resource(config);
+        app.init_resource::<BattlePair>();
+        app.init_resource::<Ambushes>();
+        app.add_systems(Update, plot_ambushes);
+
+        let sect = SectMember {
+            sect: app.world_mut().spawn_empty().id(),
+        };
+        let mut spawn = |level, cultivation, sect: Option<SectMember>| {
+            let mut entity = app.world_mut().spawn((
+                Cultivation { level, cultivation },
+                Courage { courage: 0.9 },
+                Life {
+                    age: 20,
+                    lifespan: 1000,
+                    alive: true,
+                    ticks: 0,
+                },
+            ));
+            if let Some(sect) = sect {
+                entity.insert(sect);
+            }
+        };
+        // The only cultivator stronger than the plotter is a sect mate, the others would join
+        // but are of another level.
+        spawn(Level::Foundation, 1000, Some(sect));
+        spawn(Level::Foundation, 50, Some(sect));
+        for _ in 0..3 {
+            spawn(Level::GoldenCore, 100, None);
+        }
+        app.update();
+
+        assert!(app.world().resource::<Ambushes>().0.is_empty());
+    }
+
+    #[test]
+    fn test_allies_are_peers() {
+        let mut app = App::new();
+        app.add_plugins(EntropyPlugin::<WyRand>::with_seed(42u64.to_le_bytes()));
+        let mut config = Config::default();
+        config.ambush.enabled = true;
+        config.ambush.rate = 1.0;
+        app.insert_resource(config);
+        app.init_resource::<BattlePair>();
+        app.init_resource::<Ambushes>();
+        app.add_systems(Update, plot_ambushes);
+
+        let mut spawn = |level, cultivation| {
+            app.world_mut()
+                .spawn((
+                    Cultivation { level, cultivation },
+                    Courage { courage: 0.9 },
+                    Life {
+                        age: 20,
+                        lifespan: 1000,
+                        alive: true,
+                        ticks: 0,
+                    },
+                ))
+                .id()
+        };
+        let outlier = spawn(Level::Foundation, 1000);
+        let weak = [spawn(Level::Foundation, 100), spawn(Level::Foundation, 100)];
+        // Would make the ambush a sure thing, but does not fight below their level.
+        let elder = spawn(Level::GoldenCore, 100_000);
+        app.update();
+
+        let ambushes = &app.world().resource::<Ambushes>().0;
+        assert_eq!(ambushes.len(), 1);
+        assert_eq!(ambushes[0].target, outlier);
+        assert!(!ambushes[0].attackers.contains(&elder));
+        assert!(ambushes[0].attackers.iter().all(|a| weak.contains(a)));
+    }
+}
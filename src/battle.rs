@@ -1,71 +1,556 @@
-use bevy::{ecs::query::QueryData, prelude::*};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::Hash;
+
+use bevy::ecs::{query::QueryData, system::SystemParam};
+use bevy::prelude::*;
 use bevy_prng::WyRand;
 use bevy_rand::global::GlobalEntropy;
-use itertools::Itertools;
 use rand::Rng;
 use rand::seq::SliceRandom;
 
+use crate::biography::Biography;
+use crate::config::{CombatConfig, Config};
 use crate::cultivation::Cultivation;
+use crate::demonic::{Demonic, absorb};
+use crate::injury::Injury;
+use crate::item::{Inventory, Item};
+use crate::level::Level;
 use crate::life::Life;
+use crate::partition::par_map;
+use crate::perception::{Concealment, perceive};
+use crate::region::Position;
+use crate::seclusion::Secluded;
+use crate::sect::SectMember;
 use crate::system::GamePlay;
+use crate::vengeance::Grudge;
 
 #[derive(Component)]
 pub struct Courage {
     pub courage: f64,
 }
 
+// Cultivators who can be drawn into a fight.
+pub(crate) type Available = (Without<Injury>, Without<Secluded>);
+
 #[derive(QueryData)]
 #[query_data(mutable)]
 pub struct BattleQuery {
-    cultivation: &'static mut Cultivation,
-    battle: &'static Courage,
-    life: &'static mut Life,
-    entity: Entity,
+    pub cultivation: &'static mut Cultivation,
+    pub battle: &'static Courage,
+    pub life: &'static mut Life,
+    pub sect: Option<&'static SectMember>,
+    pub position: Option<&'static Position>,
+    pub grudge: Option<&'static Grudge>,
+    pub concealment: Option<&'static Concealment>,
+    pub inventory: Option<&'static Inventory>,
+    pub demonic: Option<&'static Demonic>,
+    pub biography: Option<&'static mut Biography>,
+    pub entity: Entity,
 }
 
-fn will_battle(a: &BattleQueryReadOnlyItem, b: &BattleQueryReadOnlyItem) -> bool {
-    let win_rate = a.cultivation.get_win_rate(&b.cultivation);
-    a.battle.courage > 1.0 - win_rate
+impl BattleQueryItem<'_> {
+    // Take `amount` of cultivation from a defeated opponent.
+    pub(crate) fn gain(&mut self, amount: u64) {
+        self.cultivation.cultivation += amount;
+        if let Some(biography) = self.biography.as_mut() {
+            biography.battle_gain += amount;
+        }
+    }
 }
 
-#[derive(Resource, Default)]
-struct BattlePair(Vec<(Entity, Entity)>);
-
-fn pair(query: Query<BattleQuery>, mut rng: GlobalEntropy<WyRand>, mut pairs: ResMut<BattlePair>) {
-    let mut players: Vec<_> = query.iter().map(|i| i.entity).collect();
-    players.shuffle(&mut rng);
-    pairs.0 = players.chunks_exact(2).map(|l| (l[0], l[1])).collect();
-}
-
-fn filter_battle(data: Query<BattleQueryReadOnly>, mut pairs: ResMut<BattlePair>) {
-    pairs.0 = pairs
-        .0
-        .iter()
-        .filter(|(a, b)| {
-            let (a, b) = (data.get(*a).unwrap(), data.get(*b).unwrap());
-            if a.cultivation.level != b.cultivation.level {
-                return false;
+pub(crate) fn will_battle(a: &BattleQueryReadOnlyItem, b: &BattleQueryReadOnlyItem) -> bool {
+    judge(a, b, b.cultivation)
+}
+
+// Decision of a against b, whose cultivation appears to a as `seen`.
+fn judge(a: &BattleQueryReadOnlyItem, b: &BattleQueryReadOnlyItem, seen: &Cultivation) -> bool {
+    let win_rate = a.cultivation.get_win_rate(seen);
+    let mut courage = match a.grudge {
+        Some(grudge) if grudge.killers.contains(&b.entity) => a.battle.courage + grudge.courage,
+        _ => a.battle.courage,
+    };
+    if let (None, Some(demonic)) = (a.demonic, b.demonic) {
+        courage += demonic.infamy;
+    }
+    courage > 1.0 - win_rate
+}
+
+pub(crate) fn escape_rate(fleer: &Cultivation, chaser: &Cultivation, factor: f64) -> f64 {
+    (factor * fleer.get_win_rate(chaser)).min(1.0)
+}
+
+// Outcome of a multi-round fight between fighters 0 and 1.
+#[derive(Debug)]
+struct Combat {
+    winner: usize,
+    // The loser broke away before being defeated.
+    fled: bool,
+    // Cultivation each fighter loses to the wounds taken.
+    wounds: [u64; 2],
+}
+
+fn fight(rng: &mut impl Rng, combat: &CombatConfig, fighters: [&Cultivation; 2]) -> Combat {
+    let level = fighters[0].level;
+    let damage = combat.damage(level);
+    let full = fighters.map(|c| (c.cultivation as f64).max(1.0));
+    let mut health = full;
+    let mut outcome = None;
+    for _ in 0..combat.rounds(level) {
+        for i in 0..2 {
+            health[1 - i] -= full[i] * damage * rng.random_range(0.0..2.0);
+        }
+        if health.iter().any(|h| *h <= 0.0) {
+            break;
+        }
+        // The more battered fighter tries to break away first.
+        let weaker = if health[0] / full[0] < health[1] / full[1] {
+            0
+        } else {
+            1
+        };
+        if health[weaker] < combat.flee_health * full[weaker] {
+            let rate = combat.flee_factor * health[weaker] / (health[0] + health[1]);
+            if rng.random::<f64>() < rate.min(1.0) {
+                outcome = Some((1 - weaker, true));
+                break;
             }
-            will_battle(&a, &b) || will_battle(&b, &a)
-        })
-        .cloned()
+        }
+    }
+    let (winner, fled) = outcome.unwrap_or_else(|| {
+        let winner = if health[0] / full[0] >= health[1] / full[1] {
+            0
+        } else {
+            1
+        };
+        (winner, false)
+    });
+    let wounds = [0, 1].map(|i| ((full[i] - health[i].max(0.0)) * combat.wound) as u64);
+    Combat {
+        winner,
+        fled,
+        wounds,
+    }
+}
+
+#[derive(Event)]
+pub struct EscapeEvent {
+    pub fleer: Entity,
+    pub chaser: Entity,
+    // The fleer burnt an escape talisman.
+    pub talisman: bool,
+}
+
+// Emitted for a cultivator who fought only because they misjudged the opponent.
+#[derive(Event)]
+pub struct MisjudgeEvent {
+    pub entity: Entity,
+    pub died: bool,
+}
+
+#[derive(Event)]
+pub struct BattleEvent {
+    pub winner: Entity,
+    pub loser: Entity,
+    pub killed: bool,
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct BattlePair {
+    pairs: Vec<(Entity, Entity)>,
+    // Encounters left without a partner because their partition drew an odd number of them.
+    pub unmatched: usize,
+    // Meetings, as (cultivator, opponent), where the perceived win rate made the cultivator fight
+    // when the true one would not.
+    misjudged: HashSet<(Entity, Entity)>,
+}
+
+impl BattlePair {
+    // Force a battle between a and b, dropping whatever pairs they were already in.
+    pub fn claim(&mut self, a: Entity, b: Entity) {
+        self.withdraw(&[a, b]);
+        self.pairs.push((a, b));
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    // Drop every pair involving one of the busy cultivators.
+    pub fn withdraw(&mut self, busy: &[Entity]) {
+        self.pairs
+            .retain(|(x, y)| !busy.contains(x) && !busy.contains(y));
+    }
+}
+
+// Knuth's method, fine for the handful of encounters a cultivator has in a year.
+fn poisson(rng: &mut impl Rng, lambda: f64) -> usize {
+    let limit = (-lambda).exp();
+    let mut k = 0;
+    let mut p: f64 = rng.random();
+    while p > limit {
+        k += 1;
+        p *= rng.random::<f64>();
+    }
+    k
+}
+
+// Draws the encounters of the cultivators of one cell and pairs them up, returning the pairs
+// and the number of encounters left without a partner.
+pub(crate) fn pair_partition<T: Copy + PartialEq>(
+    rng: &mut impl Rng,
+    config: &Config,
+    members: Vec<(T, Level)>,
+) -> (Vec<(T, T)>, usize) {
+    let time = &config.time;
+    let mut players = Vec::new();
+    for (member, level) in members {
+        let encounters = if config.encounter.enabled {
+            poisson(rng, time.rate(config.encounter.rate(level)))
+        } else if time.ticks_per_year == 1 || rng.random::<f64>() < time.rate(1.0) {
+            1
+        } else {
+            0
+        };
+        players.extend(std::iter::repeat_n(member, encounters));
+    }
+    players.shuffle(rng);
+    // With several encounters a year a cultivator may draw themselves, such meetings are void.
+    let pairs: Vec<_> = players
+        .chunks_exact(2)
+        .map(|l| (l[0], l[1]))
+        .filter(|(a, b)| a != b)
         .collect();
+    (pairs, players.len() % 2)
+}
+
+pub(crate) fn pair(
+    query: Query<BattleQuery, Available>,
+    mut rng: GlobalEntropy<WyRand>,
+    mut pairs: ResMut<BattlePair>,
+    config: Res<Config>,
+) {
+    // Cultivators only meet locally, so each cell is paired on its own. Meetings across levels
+    // use up encounters and are dropped by `filter_battle`.
+    let mut cells: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for i in query.iter() {
+        let member = (i.entity, i.cultivation.level);
+        cells.entry(i.position.copied()).or_default().push(member);
+    }
+    let config = &*config;
+    let cells = par_map(rng.random(), cells, |rng, _, members| {
+        pair_partition(rng, config, members)
+    });
+    pairs.unmatched = cells.iter().map(|(_, unmatched)| unmatched).sum();
+    pairs.pairs = cells.into_iter().flat_map(|(pairs, _)| pairs).collect();
+}
+
+enum Meeting {
+    Fight,
+    Part,
+    Escape(EscapeEvent),
+}
+
+fn meet<'w>(
+    rng: &mut impl Rng,
+    config: &Config,
+    a: BattleQueryReadOnlyItem<'w>,
+    b: BattleQueryReadOnlyItem<'w>,
+    misjudged: &mut Vec<(Entity, Entity)>,
+) -> Meeting {
+    if a.sect.is_some() && a.sect == b.sect {
+        return Meeting::Part;
+    }
+    let decisions = if config.perception.enabled {
+        let a_sees = perceive(rng, &config.perception, &b);
+        let b_sees = perceive(rng, &config.perception, &a);
+        let decisions = (judge(&a, &b, &a_sees), judge(&b, &a, &b_sees));
+        if decisions.0 && !will_battle(&a, &b) {
+            misjudged.push((a.entity, b.entity));
+        }
+        if decisions.1 && !will_battle(&b, &a) {
+            misjudged.push((b.entity, a.entity));
+        }
+        decisions
+    } else {
+        (will_battle(&a, &b), will_battle(&b, &a))
+    };
+    let (fleer, chaser) = match decisions {
+        (false, false) => return Meeting::Part,
+        (true, true) => return Meeting::Fight,
+        (true, false) => (b, a),
+        (false, true) => (a, b),
+    };
+    match flee(rng, config, &fleer, &chaser) {
+        Some(ev) => Meeting::Escape(ev),
+        None => Meeting::Fight,
+    }
+}
+
+// The roll of a cultivator running from a fight, helped by a talisman when they carry one.
+pub(crate) fn flee(
+    rng: &mut impl Rng,
+    config: &Config,
+    fleer: &BattleQueryReadOnlyItem,
+    chaser: &BattleQueryReadOnlyItem,
+) -> Option<EscapeEvent> {
+    let talisman = config.item.enabled
+        && fleer
+            .inventory
+            .is_some_and(|i| i.items.contains(&Item::EscapeTalisman));
+    let rate = if talisman {
+        config.item.talisman_rate
+    } else {
+        escape_rate(fleer.cultivation, chaser.cultivation, config.escape.factor)
+    };
+    (rate > 0.0 && rng.random::<f64>() < rate).then_some(EscapeEvent {
+        fleer: fleer.entity,
+        chaser: chaser.entity,
+        talisman,
+    })
 }
 
-fn battle(mut rng: GlobalEntropy<WyRand>, mut data: Query<BattleQuery>, battles: Res<BattlePair>) {
-    for pair in &battles.0 {
-        let prob: f64 = rng.random();
-        let [mut winner, mut loser] = data.get_many_mut([pair.0, pair.1]).unwrap();
-        if prob > winner.cultivation.get_win_rate(&loser.cultivation) {
-            (winner, loser) = (loser, winner);
+pub(crate) fn filter_battle(
+    data: Query<BattleQueryReadOnly>,
+    mut pairs: ResMut<BattlePair>,
+    mut rng: GlobalEntropy<WyRand>,
+    config: Res<Config>,
+    mut ev_escape: EventWriter<EscapeEvent>,
+) {
+    let BattlePair {
+        pairs, misjudged, ..
+    } = &mut *pairs;
+    // Pairs of one cell and level are judged apart from the others.
+    let mut partitions: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for (a, b) in pairs.drain(..) {
+        let (x, y) = (data.get(a).unwrap(), data.get(b).unwrap());
+        if x.cultivation.level == y.cultivation.level {
+            let key = (x.position.copied(), x.cultivation.level);
+            partitions.entry(key).or_default().push((a, b));
+        }
+    }
+    let (data, config) = (&data, &*config);
+    let partitions = par_map(rng.random(), partitions, |rng, _, candidates| {
+        let mut kept = Vec::new();
+        let mut misjudged = Vec::new();
+        let mut escapes = Vec::new();
+        for (a, b) in candidates {
+            let (x, y) = (data.get(a).unwrap(), data.get(b).unwrap());
+            match meet(rng, config, x, y, &mut misjudged) {
+                Meeting::Fight => kept.push((a, b)),
+                Meeting::Part => {}
+                Meeting::Escape(ev) => escapes.push(ev),
+            }
+        }
+        (kept, misjudged, escapes)
+    });
+    misjudged.clear();
+    for (kept, misjudged_here, escapes) in partitions {
+        pairs.extend(kept);
+        misjudged.extend(misjudged_here);
+        ev_escape.write_batch(escapes);
+    }
+}
+
+#[derive(SystemParam)]
+pub(crate) struct BattleEvents<'w> {
+    battle: EventWriter<'w, BattleEvent>,
+    misjudge: EventWriter<'w, MisjudgeEvent>,
+    escape: EventWriter<'w, EscapeEvent>,
+}
+
+// A fighter's state while battles are resolved away from the world.
+#[derive(Debug, Clone)]
+pub(crate) struct Fighter {
+    pub cultivation: Cultivation,
+    pub life: Life,
+    pub demonic: bool,
+    // Cultivation taken from the defeated.
+    pub gain: u64,
+    pub injured: bool,
+}
+
+pub(crate) enum Outcome<K> {
+    Fled { fleer: K, chaser: K },
+    Fought { winner: K, loser: K, killed: bool },
+}
+
+// Fights out the pairs of one partition in order, a cultivator may be in several of them.
+pub(crate) fn resolve<K: Copy + Eq + Hash>(
+    rng: &mut impl Rng,
+    config: &Config,
+    pairs: &[(K, K)],
+    fighters: &mut HashMap<K, Fighter>,
+) -> Vec<Outcome<K>> {
+    let injury = &config.injury;
+    let mut outcomes = Vec::new();
+    for &(a, b) in pairs {
+        let [Some(mut winner), Some(mut loser)] = fighters.get_disjoint_mut([&a, &b]) else {
+            continue;
+        };
+        let (mut winner_id, mut loser_id) = (a, b);
+        // Cultivators with several encounters may already be dead or wounded this year.
+        let fallen = |f: &Fighter| !f.life.alive || f.injured;
+        if fallen(winner) || fallen(loser) {
+            continue;
+        }
+        let fled = if config.combat.enabled {
+            let fighters = [&winner.cultivation, &loser.cultivation];
+            let combat = fight(rng, &config.combat, fighters);
+            for (fighter, wound) in [&mut *winner, &mut *loser].into_iter().zip(combat.wounds) {
+                let cultivation = &mut fighter.cultivation.cultivation;
+                *cultivation = cultivation.saturating_sub(wound).max(1);
+            }
+            if combat.winner == 1 {
+                (winner, loser) = (loser, winner);
+                (winner_id, loser_id) = (loser_id, winner_id);
+            }
+            combat.fled
+        } else {
+            let prob: f64 = rng.random();
+            if prob > winner.cultivation.get_win_rate(&loser.cultivation) {
+                (winner, loser) = (loser, winner);
+                (winner_id, loser_id) = (loser_id, winner_id);
+            }
+            false
+        };
+        if fled {
+            outcomes.push(Outcome::Fled {
+                fleer: loser_id,
+                chaser: winner_id,
+            });
+            continue;
+        }
+        let spoils = absorb(
+            &config.demonic,
+            winner.demonic,
+            loser.cultivation.cultivation,
+        );
+        winner.cultivation.cultivation += spoils;
+        winner.gain += spoils;
+        let killed =
+            !injury.enabled || rng.random::<f64>() < injury.death_rate(loser.cultivation.level);
+        if killed {
+            loser.life.alive = false;
+        } else {
+            let loss = loser.cultivation.cultivation as f64 * injury.cultivation_loss;
+            loser.cultivation.cultivation -= loss as u64;
+            let lifespan = loser.life.lifespan.saturating_sub(injury.lifespan_loss);
+            loser.life.lifespan = lifespan.max(loser.life.age + 1);
+            loser.injured = true;
+        }
+        outcomes.push(Outcome::Fought {
+            winner: winner_id,
+            loser: loser_id,
+            killed,
+        });
+    }
+    outcomes
+}
+
+pub(crate) fn battle(
+    mut commands: Commands,
+    mut rng: GlobalEntropy<WyRand>,
+    mut data: Query<BattleQuery>,
+    battles: Res<BattlePair>,
+    config: Res<Config>,
+    mut events: BattleEvents,
+) {
+    // Filtered pairs are between peers of one cell, so cells and levels share no fighters and
+    // are fought in parallel. Pairs claimed across them afterwards are fought once they are settled.
+    let mut peers: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    let mut mixed: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    let key = |i: BattleQueryReadOnlyItem| (i.position.copied(), i.cultivation.level);
+    for &(a, b) in &battles.pairs {
+        let (x, y) = (key(data.get(a).unwrap()), key(data.get(b).unwrap()));
+        if x == y {
+            peers.entry(Some(x)).or_default().push((a, b));
+        } else {
+            mixed.entry(None).or_default().push((a, b));
+        }
+    }
+    let mut injured = HashSet::new();
+    let seed: u64 = rng.random();
+    for (round, partitions) in [peers, mixed].into_iter().enumerate() {
+        let (data_ro, config) = (&data.as_readonly(), &*config);
+        let injured_ro = &injured;
+        let resolved = par_map(
+            seed.wrapping_add(round as u64),
+            partitions,
+            |rng, _, pairs| {
+                let mut fighters = HashMap::new();
+                for e in pairs.iter().flat_map(|(a, b)| [*a, *b]) {
+                    let i = data_ro.get(e).unwrap();
+                    fighters.entry(e).or_insert_with(|| Fighter {
+                        cultivation: i.cultivation.clone(),
+                        life: i.life.clone(),
+                        demonic: i.demonic.is_some(),
+                        gain: 0,
+                        injured: injured_ro.contains(&e),
+                    });
+                }
+                let outcomes = resolve(rng, config, &pairs, &mut fighters);
+                // Written back in entity order, so injuries move entities between tables in the
+                // same order, and queries visit them in the same order, on every run.
+                let mut fighters: Vec<_> = fighters.into_iter().collect();
+                fighters.sort_unstable_by_key(|(entity, _)| *entity);
+                (fighters, outcomes)
+            },
+        );
+        for (fighters, outcomes) in resolved {
+            for (entity, fighter) in fighters {
+                let mut i = data.get_mut(entity).unwrap();
+                *i.cultivation = fighter.cultivation;
+                *i.life = fighter.life;
+                if let Some(biography) = i.biography.as_mut() {
+                    biography.battle_gain += fighter.gain;
+                }
+                if fighter.injured && injured.insert(entity) {
+                    commands.entity(entity).insert(Injury {
+                        ticks: config.injury.recovery_years * config.time.ticks_per_year,
+                    });
+                }
+            }
+            for outcome in outcomes {
+                match outcome {
+                    Outcome::Fled { fleer, chaser } => {
+                        events.escape.write(EscapeEvent {
+                            fleer,
+                            chaser,
+                            talisman: false,
+                        });
+                    }
+                    Outcome::Fought {
+                        winner,
+                        loser,
+                        killed,
+                    } => {
+                        for (entity, opponent, lost) in
+                            [(winner, loser, false), (loser, winner, true)]
+                        {
+                            if battles.misjudged.contains(&(entity, opponent)) {
+                                events.misjudge.write(MisjudgeEvent {
+                                    entity,
+                                    died: lost && killed,
+                                });
+                            }
+                        }
+                        events.battle.write(BattleEvent {
+                            winner,
+                            loser,
+                            killed,
+                        });
+                    }
+                }
+            }
         }
-        winner.cultivation.cultivation += loser.cultivation.cultivation / 10;
-        loser.life.alive = false;
     }
 }
 
 pub fn battle_plugin(app: &mut App) {
     app.init_resource::<BattlePair>();
+    app.add_event::<EscapeEvent>();
+    app.add_event::<BattleEvent>();
+    app.add_event::<MisjudgeEvent>();
     app.add_systems(
         Update,
         (
@@ -80,26 +565,42 @@ pub fn battle_plugin(app: &mut App) {
 mod tests {
     use super::*;
     use crate::level::Level;
+    use bevy_rand::plugin::EntropyPlugin;
+    use rand::SeedableRng;
 
     #[test]
     fn test_will_battle() {
         let mut world = World::new();
-        let a_entity = world.spawn((
-            Cultivation {
-                level: Level::Foundation,
-                cultivation: 100,
-            },
-            Courage { courage: 0.8 },
-            Life { age: 0, lifespan: 100, alive: true },
-        )).id();
-        let b_entity = world.spawn((
-            Cultivation {
-                level: Level::Foundation,
-                cultivation: 100,
-            },
-            Courage { courage: 0.2 },
-            Life { age: 0, lifespan: 100, alive: true },
-        )).id();
+        let a_entity = world
+            .spawn((
+                Cultivation {
+                    level: Level::Foundation,
+                    cultivation: 100,
+                },
+                Courage { courage: 0.8 },
+                Life {
+                    age: 0,
+                    lifespan: 100,
+                    alive: true,
+                    ticks: 0,
+                },
+            ))
+            .id();
+        let b_entity = world
+            .spawn((
+                Cultivation {
+                    level: Level::Foundation,
+                    cultivation: 100,
+                },
+                Courage { courage: 0.2 },
+                Life {
+                    age: 0,
+                    lifespan: 100,
+                    alive: true,
+                    ticks: 0,
+                },
+            ))
+            .id();
 
         let mut query = world.query::<BattleQuery>();
         let a = query.get(&world, a_entity).unwrap();
@@ -108,4 +609,360 @@ mod tests {
         assert!(will_battle(&a, &b));
         assert!(!will_battle(&b, &a));
     }
+
+    #[test]
+    fn test_will_battle_grudge() {
+        let mut world = World::new();
+        let killer = world
+            .spawn((
+                Cultivation {
+                    level: Level::Foundation,
+                    cultivation: 100,
+                },
+                Courage { courage: 0.8 },
+                Life {
+                    age: 0,
+                    lifespan: 100,
+                    alive: true,
+                    ticks: 0,
+                },
+            ))
+            .id();
+        let avenger = world
+            .spawn((
+                Cultivation {
+                    level: Level::Foundation,
+                    cultivation: 100,
+                },
+                Courage { courage: 0.2 },
+                Life {
+                    age: 0,
+                    lifespan: 100,
+                    alive: true,
+                    ticks: 0,
+                },
+                Grudge {
+                    killers: vec![killer],
+                    courage: 0.5,
+                },
+            ))
+            .id();
+
+        let mut query = world.query::<BattleQuery>();
+        let killer = query.get(&world, killer).unwrap();
+        let avenger = query.get(&world, avenger).unwrap();
+
+        assert!(will_battle(&avenger, &killer));
+    }
+
+    #[test]
+    fn test_escape_rate() {
+        let mut world = World::new();
+        let weak = world
+            .spawn((
+                Cultivation {
+                    level: Level::Foundation,
+                    cultivation: 100,
+                },
+                Courage { courage: 0.1 },
+                Life {
+                    age: 0,
+                    lifespan: 100,
+                    alive: true,
+                    ticks: 0,
+                },
+            ))
+            .id();
+        let strong = world
+            .spawn((
+                Cultivation {
+                    level: Level::Foundation,
+                    cultivation: 300,
+                },
+                Courage { courage: 0.9 },
+                Life {
+                    age: 0,
+                    lifespan: 100,
+                    alive: true,
+                    ticks: 0,
+                },
+            ))
+            .id();
+
+        let mut query = world.query::<BattleQuery>();
+        let weak = query.get(&world, weak).unwrap();
+        let strong = query.get(&world, strong).unwrap();
+
+        assert_eq!(escape_rate(weak.cultivation, strong.cultivation, 1.0), 0.25);
+        assert_eq!(escape_rate(strong.cultivation, weak.cultivation, 1.0), 0.75);
+        assert_eq!(escape_rate(weak.cultivation, strong.cultivation, 10.0), 1.0);
+    }
+
+    #[test]
+    fn test_pair_within_cell() {
+        let mut app = App::new();
+        app.add_plugins(EntropyPlugin::<WyRand>::default());
+        app.init_resource::<BattlePair>();
+        app.init_resource::<Config>();
+        app.add_systems(Update, pair);
+        for x in 0..10 {
+            // Peers are not sought out, a cell is paired whatever the levels in it.
+            let level = if x < 5 {
+                Level::Foundation
+            } else {
+                Level::GoldenCore
+            };
+            app.world_mut().spawn((
+                Cultivation {
+                    level,
+                    cultivation: 10,
+                },
+                Courage { courage: 0.5 },
+                Life {
+                    age: 20,
+                    lifespan: 100,
+                    alive: true,
+                    ticks: 0,
+                },
+                Position { x: x % 2, y: 0 },
+            ));
+        }
+        app.update();
+
+        let pairs = &app.world().resource::<BattlePair>().pairs;
+        assert_eq!(pairs.len(), 4);
+        for (a, b) in pairs {
+            let a = app.world().get::<Position>(*a).unwrap();
+            let b = app.world().get::<Position>(*b).unwrap();
+            assert_eq!(a, b);
+        }
+    }
+
+    #[test]
+    fn test_pair_multiple_encounters() {
+        let mut app = App::new();
+        app.add_plugins(EntropyPlugin::<WyRand>::with_seed(42u64.to_le_bytes()));
+        app.init_resource::<BattlePair>();
+        let mut config = Config::default();
+        config.encounter.enabled = true;
+        config.encounter.rate = vec![5.0; 7];
+        app.insert_resource(config);
+        app.add_systems(Update, pair);
+        for _ in 0..10 {
+            app.world_mut().spawn((
+                Cultivation {
+                    level: Level::Foundation,
+                    cultivation: 10,
+                },
+                Courage { courage: 0.5 },
+                Life {
+                    age: 20,
+                    lifespan: 100,
+                    alive: true,
+                    ticks: 0,
+                },
+            ));
+        }
+        app.update();
+
+        let pairs = app.world().resource::<BattlePair>();
+        assert!(pairs.pairs.len() > 10);
+        assert!(pairs.unmatched <= 1);
+        assert!(pairs.pairs.iter().all(|(a, b)| a != b));
+    }
+
+    #[test]
+    fn test_poisson_mean() {
+        let mut rng = WyRand::seed_from_u64(42);
+        let total: usize = (0..10000).map(|_| poisson(&mut rng, 3.0)).sum();
+        assert!((total as f64 / 10000.0 - 3.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_battle_injury() {
+        let mut app = App::new();
+        app.add_plugins(EntropyPlugin::<WyRand>::default());
+        app.add_event::<BattleEvent>();
+        app.add_event::<MisjudgeEvent>();
+        app.add_event::<EscapeEvent>();
+        let mut config = Config::default();
+        config.injury.enabled = true;
+        config.injury.death_rate = vec![0.0; 7];
+        app.insert_resource(config);
+
+        let a = app
+            .world_mut()
+            .spawn((
+                Cultivation {
+                    level: Level::Foundation,
+                    cultivation: 100,
+                },
+                Courage { courage: 0.5 },
+                Life {
+                    age: 50,
+                    lifespan: 100,
+                    alive: true,
+                    ticks: 0,
+                },
+            ))
+            .id();
+        let b = app
+            .world_mut()
+            .spawn((
+                Cultivation {
+                    level: Level::Foundation,
+                    cultivation: 100,
+                },
+                Courage { courage: 0.5 },
+                Life {
+                    age: 50,
+                    lifespan: 100,
+                    alive: true,
+                    ticks: 0,
+                },
+            ))
+            .id();
+        app.insert_resource(BattlePair {
+            pairs: vec![(a, b)],
+            ..default()
+        });
+        app.add_systems(Update, battle);
+        app.update();
+
+        let injured: Vec<_> = [a, b]
+            .into_iter()
+            .filter(|e| app.world().get::<Injury>(*e).is_some())
+            .collect();
+        assert_eq!(injured.len(), 1);
+        let loser = app.world().get::<Cultivation>(injured[0]).unwrap();
+        assert_eq!(loser.cultivation, 90);
+        let life = app.world().get::<Life>(injured[0]).unwrap();
+        assert!(life.alive);
+        assert_eq!(life.lifespan, 90);
+    }
+
+    #[test]
+    fn test_battle_misjudged_meeting() {
+        let mut app = App::new();
+        app.add_plugins(EntropyPlugin::<WyRand>::with_seed(42u64.to_le_bytes()));
+        app.add_event::<BattleEvent>();
+        app.add_event::<MisjudgeEvent>();
+        app.add_event::<EscapeEvent>();
+        app.insert_resource(Config::default());
+        let mut spawn = |cultivation| {
+            app.world_mut()
+                .spawn((
+                    Cultivation {
+                        level: Level::Foundation,
+                        cultivation,
+                    },
+                    Courage { courage: 0.5 },
+                    Life {
+                        age: 50,
+                        lifespan: 100,
+                        alive: true,
+                        ticks: 0,
+                    },
+                ))
+                .id()
+        };
+        let (a, b, c) = (spawn(1_000_000), spawn(10), spawn(10));
+        // Two encounters for a this year, only the one with b misjudged.
+        app.insert_resource(BattlePair {
+            pairs: vec![(a, b), (a, c)],
+            misjudged: HashSet::from([(a, b)]),
+            ..default()
+        });
+        app.add_systems(Update, battle);
+        app.update();
+
+        let battles = app.world().resource::<Events<BattleEvent>>();
+        assert_eq!(battles.len(), 2);
+        let misjudges = app.world().resource::<Events<MisjudgeEvent>>();
+        let misjudges: Vec<_> = misjudges.iter_current_update_events().collect();
+        assert_eq!(misjudges.len(), 1);
+        assert_eq!(misjudges[0].entity, a);
+        assert!(!misjudges[0].died);
+    }
+
+    #[test]
+    fn test_fight() {
+        let mut rng = WyRand::seed_from_u64(42);
+        let strong = Cultivation {
+            level: Level::Foundation,
+            cultivation: 1000,
+        };
+        let weak = Cultivation {
+            level: Level::Foundation,
+            cultivation: 100,
+        };
+        let combat = CombatConfig {
+            rounds: vec![100; 7],
+            flee_health: 0.0,
+            ..default()
+        };
+        let result = fight(&mut rng, &combat, [&strong, &weak]);
+        assert_eq!(result.winner, 0);
+        assert!(!result.fled);
+        assert!(result.wounds[0] > 0);
+        assert!(result.wounds[1] >= 50);
+
+        let combat = CombatConfig {
+            damage: vec![0.02; 7],
+            flee_health: 1.0,
+            flee_factor: 10.0,
+            ..default()
+        };
+        let result = fight(&mut rng, &combat, [&strong, &weak]);
+        assert_eq!(result.winner, 0);
+        assert!(result.fled);
+    }
+
+    #[test]
+    fn test_battle_reproducible() {
+        let run = || {
+            let mut app = App::new();
+            app.add_plugins(EntropyPlugin::<WyRand>::with_seed(42u64.to_le_bytes()));
+            app.add_event::<BattleEvent>();
+            app.add_event::<MisjudgeEvent>();
+            app.add_event::<EscapeEvent>();
+            app.init_resource::<BattlePair>();
+            let mut config = Config::default();
+            config.encounter.enabled = true;
+            config.combat.enabled = true;
+            app.insert_resource(config);
+            app.add_systems(Update, (pair, filter_battle, battle).chain());
+            let levels = [Level::Foundation, Level::GoldenCore, Level::NascentSoul];
+            for i in 0..300 {
+                app.world_mut().spawn((
+                    Cultivation {
+                        level: levels[i % 3],
+                        cultivation: 10 + i as u64,
+                    },
+                    Courage {
+                        courage: (i % 10) as f64 / 10.0,
+                    },
+                    Life {
+                        age: 20,
+                        lifespan: 100,
+                        alive: true,
+                        ticks: 0,
+                    },
+                ));
+            }
+            for _ in 0..3 {
+                app.update();
+            }
+            let mut query = app.world_mut().query::<(Entity, &Cultivation, &Life)>();
+            let mut state: Vec<_> = query
+                .iter(app.world())
+                .map(|(e, c, l)| (e, c.cultivation, l.alive))
+                .collect();
+            state.sort();
+            state
+        };
+        let first = run();
+        assert!(first.iter().any(|(_, _, alive)| !alive));
+        assert_eq!(first, run());
+    }
 }
@@ -1,30 +1,234 @@
-use std::time::Duration;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
 
-use crate::system::GamePlay;
+use crate::columnar::Columns;
+use crate::config::{Backend, Config};
+use crate::cultivation::Cultivation;
+use crate::export::Exporter;
+use crate::spawn::DeathEvent;
+use crate::system::{GamePlay, GlobalState, advance_time};
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
+use serde::Serialize;
+
+// The sets of a tick in the order they are played, with the names they are reported under.
+const PHASES: [(GamePlay, &str); 5] = [
+    (GamePlay::Spawn, "生成"),
+    (GamePlay::Pair, "配对"),
+    (GamePlay::FilterPair, "筛选"),
+    (GamePlay::Battle, "战斗"),
+    (GamePlay::Finish, "结算"),
+];
 
 #[derive(Resource)]
 struct Benchmark {
     timer: Timer,
     cycles: u64,
+    // When the last set of the tick ended.
+    lap: Instant,
+    // Time spent in each set since the last report, indexed as `PHASES`.
+    phases: [Duration; 5],
+    spawns: usize,
+    despawns: usize,
+}
+
+// One report of the benchmark, averaged over the ticks since the previous one.
+#[derive(Debug, Serialize)]
+struct Metrics {
+    year: u64,
+    cycles_per_sec: f64,
+    // Microseconds per tick by set.
+    phase_us: BTreeMap<String, f64>,
+    alive: usize,
+    spawns: f64,
+    despawns: f64,
+    resident_bytes: Option<u64>,
+}
+
+// Time since the previous lap is charged to `phase`, the lap opening the tick charges nothing.
+fn lap(phase: Option<usize>) -> impl FnMut(ResMut<Benchmark>) {
+    move |mut benchmark| {
+        let now = Instant::now();
+        let elapsed = now - benchmark.lap;
+        if let Some(phase) = phase {
+            benchmark.phases[phase] += elapsed;
+        }
+        benchmark.lap = now;
+    }
+}
+
+// Resident memory of the process, where the platform reports it.
+fn resident_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+// Cultivators alive and spawned, counted on whichever backend plays them.
+#[derive(SystemParam)]
+struct Population<'w, 's> {
+    cultivators: Query<'w, 's, (), With<Cultivation>>,
+    columns: Res<'w, Columns>,
+    config: Res<'w, Config>,
+}
+
+impl Population<'_, '_> {
+    fn alive(&self) -> usize {
+        match self.config.backend {
+            Backend::Ecs => self.cultivators.iter().len(),
+            Backend::Columnar => self.columns.len(),
+        }
+    }
+
+    // Spawned on this tick but not counted by `count_spawns`.
+    fn spawned(&self) -> usize {
+        match self.config.backend {
+            Backend::Ecs => 0,
+            Backend::Columnar => self.columns.spawned,
+        }
+    }
+}
+
+// Entities are counted right after they are spawned, they may die and despawn the same tick.
+fn count_spawns(spawned: Query<(), Added<Cultivation>>, mut benchmark: ResMut<Benchmark>) {
+    benchmark.spawns += spawned.iter().count();
 }
 
-fn print_benchmark(mut benchmark: ResMut<Benchmark>, time: Res<Time>) {
+fn print_benchmark(
+    mut benchmark: ResMut<Benchmark>,
+    time: Res<Time>,
+    population: Population,
+    mut ev_death: EventReader<DeathEvent>,
+    state: Res<GlobalState>,
+    config: Res<Config>,
+    mut exporter: ResMut<Exporter>,
+) {
     benchmark.timer.tick(time.delta());
     benchmark.cycles += 1;
-    if benchmark.timer.just_finished() {
-        info!(
-            "循环速度: {:.3}/sec",
-            benchmark.cycles as f64 / benchmark.timer.duration().as_secs_f64()
-        );
-        benchmark.cycles = 0;
+    benchmark.spawns += population.spawned();
+    benchmark.despawns += ev_death.read().count();
+    if !benchmark.timer.just_finished() {
+        return;
     }
+    let cycles = benchmark.cycles as f64;
+    let metrics = Metrics {
+        year: state.year,
+        cycles_per_sec: cycles / benchmark.timer.duration().as_secs_f64(),
+        phase_us: PHASES
+            .iter()
+            .zip(benchmark.phases)
+            .map(|((set, _), d)| (format!("{:?}", set), d.as_secs_f64() * 1e6 / cycles))
+            .collect(),
+        alive: population.alive(),
+        spawns: benchmark.spawns as f64 / cycles,
+        despawns: benchmark.despawns as f64 / cycles,
+        resident_bytes: resident_bytes(),
+    };
+    info!("循环速度: {:.3}/sec", metrics.cycles_per_sec);
+    let phases: Vec<_> = PHASES
+        .iter()
+        .map(|(set, name)| format!("{}{:.0}μs", name, metrics.phase_us[&format!("{:?}", set)]))
+        .collect();
+    info!("每循环耗时: {}", phases.join("，"));
+    info!(
+        "修士{}名，每循环新增{:.1}名，消亡{:.1}名，内存{}",
+        metrics.alive,
+        metrics.spawns,
+        metrics.despawns,
+        metrics
+            .resident_bytes
+            .map_or("未知".to_string(), |b| format!("{}MB", b >> 20))
+    );
+    exporter.write(&config.export, "benchmark", [&metrics]);
+    benchmark.cycles = 0;
+    benchmark.phases = default();
+    benchmark.spawns = 0;
+    benchmark.despawns = 0;
 }
 
 pub fn benchmark_system(app: &mut App) {
     app.insert_resource(Benchmark {
         timer: Timer::new(Duration::from_secs(3), TimerMode::Repeating),
         cycles: 0,
-    })
-    .add_systems(Update, print_benchmark.in_set(GamePlay::Finish));
+        lap: Instant::now(),
+        phases: default(),
+        spawns: 0,
+        despawns: 0,
+    });
+    // Laps between the sets time each of them, systems outside the sets go uncounted.
+    app.add_systems(Update, lap(None).before(GamePlay::Spawn));
+    app.add_systems(
+        Update,
+        count_spawns
+            .after(Cultivation::try_advance)
+            .in_set(GamePlay::Spawn),
+    );
+    for (i, pair) in PHASES.windows(2).enumerate() {
+        app.add_systems(
+            Update,
+            lap(Some(i))
+                .after(pair[0].0.clone())
+                .before(pair[1].0.clone()),
+        );
+    }
+    app.add_systems(
+        Update,
+        (lap(Some(PHASES.len() - 1)), print_benchmark)
+            .chain()
+            .after(GamePlay::Finish)
+            .before(advance_time),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::Level;
+    use crate::life::Life;
+    use crate::spawn::DeathCause;
+    use crate::system::game_system;
+    use bevy_prng::WyRand;
+    use bevy_rand::plugin::EntropyPlugin;
+
+    #[test]
+    fn test_benchmark() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_plugins(EntropyPlugin::<WyRand>::default());
+        app.add_event::<DeathEvent>();
+        app.init_resource::<Columns>();
+        app.init_resource::<Exporter>();
+        app.insert_resource(Config::default());
+        app.add_plugins(game_system);
+        app.add_plugins(benchmark_system);
+        app.add_systems(
+            Update,
+            (|| std::thread::sleep(Duration::from_millis(5))).in_set(GamePlay::Battle),
+        );
+        app.world_mut().send_event(DeathEvent {
+            life: Life {
+                age: 100,
+                lifespan: 100,
+                alive: false,
+                ticks: 0,
+            },
+            cause: DeathCause::OldAge,
+            demonic: false,
+            level: None,
+            born: None,
+        });
+        app.world_mut().spawn(Cultivation {
+            level: Level::Foundation,
+            cultivation: 10,
+        });
+        app.update();
+
+        let benchmark = app.world().resource::<Benchmark>();
+        assert_eq!(benchmark.cycles, 1);
+        assert_eq!(benchmark.spawns, 1);
+        assert_eq!(benchmark.despawns, 1);
+        assert!(benchmark.phases[3] >= Duration::from_millis(5));
+        assert!(benchmark.phases[3] > benchmark.phases[0]);
+    }
 }
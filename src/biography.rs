@@ -0,0 +1,92 @@
+use bevy::prelude::*;
+
+use crate::battle::{self, BattleEvent};
+use crate::cultivation::Cultivation;
+use crate::level::Level;
+use crate::system::GamePlay;
+
+// Levels of the cultivators slain by this one.
+#[derive(Component, Debug, Default)]
+pub struct Kills {
+    pub victims: Vec<Level>,
+}
+
+impl Kills {
+    pub fn count(&self) -> usize {
+        self.victims.len()
+    }
+}
+
+#[derive(Component, Debug, Default)]
+pub struct Biography {
+    pub born: u64,
+    // Levels reached and the year of each breakthrough.
+    pub breakthroughs: Vec<(Level, u64)>,
+    pub battle_gain: u64,
+    pub time_gain: u64,
+}
+
+pub(crate) fn record_kills(
+    mut ev_battle: EventReader<BattleEvent>,
+    mut killers: Query<&mut Kills>,
+    victims: Query<&Cultivation>,
+) {
+    for ev in ev_battle.read().filter(|ev| ev.killed) {
+        if let Ok(mut kills) = killers.get_mut(ev.winner)
+            && let Ok(victim) = victims.get(ev.loser)
+        {
+            kills.victims.push(victim.level);
+        }
+    }
+}
+
+pub fn biography_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        record_kills.after(battle::battle).in_set(GamePlay::Battle),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_kills() {
+        let mut app = App::new();
+        app.add_event::<BattleEvent>();
+        app.add_systems(Update, record_kills);
+
+        let killer = app.world_mut().spawn(Kills::default()).id();
+        let victim = app
+            .world_mut()
+            .spawn(Cultivation {
+                level: Level::GoldenCore,
+                cultivation: 100,
+            })
+            .id();
+        let survivor = app
+            .world_mut()
+            .spawn(Cultivation {
+                level: Level::Foundation,
+                cultivation: 10,
+            })
+            .id();
+        app.world_mut().send_event(BattleEvent {
+            winner: killer,
+            loser: victim,
+            killed: true,
+        });
+        app.world_mut().send_event(BattleEvent {
+            winner: killer,
+            loser: survivor,
+            killed: false,
+        });
+        app.update();
+
+        assert_eq!(
+            app.world().get::<Kills>(killer).unwrap().victims,
+            vec![Level::GoldenCore]
+        );
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::{BTreeMap, HashMap};
+
+use bevy::prelude::*;
+use bevy_prng::WyRand;
+use bevy_rand::global::GlobalEntropy;
+use rand::Rng;
+
+use crate::battle::{Fighter, Outcome, escape_rate, pair_partition, resolve};
+use crate::config::Config;
+use crate::cultivation::Cultivation;
+use crate::level::Level;
+use crate::life::Life;
+use crate::partition::par_map;
+use crate::region::Position;
+use crate::spawn::{DeathCause, DeathEvent, despawn_dead};
+use crate::system::{GamePlay, GlobalState, ecs_backend};
+
+// Pairs of rows keyed by the cell and level they meet in.
+type Partitions = BTreeMap<(Option<Position>, Level), Vec<(usize, usize)>>;
+
+// Cultivators kept column by column instead of one entity each. The dead are swap-removed, so
+// the columns stay dense and nothing is spawned or despawned through commands. Only the core
+// rules are played: spawning, cultivation, breakthroughs, migration, encounters, escapes,
+// combat, injuries, ascension and old age, and configs enabling any other rule are rejected.
+// The random stream is drawn in another order than by the entity systems, so a seed reproduces
+// a columnar run but not an entity one. Flows, demography and the leaderboards are not kept.
+#[derive(Resource, Default)]
+pub(crate) struct Columns {
+    pub cultivation: Vec<Cultivation>,
+    pub courage: Vec<f64>,
+    pub life: Vec<Life>,
+    // Ticks left until the injured recover.
+    pub injury: Vec<Option<u64>>,
+    pub position: Vec<Option<Position>>,
+    pub born: Vec<u64>,
+    pub ascended: Vec<bool>,
+    // Spawned on the last tick.
+    pub spawned: usize,
+    // Counted since the start of the run.
+    pub escapes: usize,
+    pub injuries: usize,
+    pub unmatched: usize,
+}
+
+impl Columns {
+    pub fn len(&self) -> usize {
+        self.life.len()
+    }
+
+    fn push(&mut self, courage: f64, position: Option<Position>, born: u64) {
+        self.cultivation.push(Cultivation {
+            level: Level::Foundation,
+            cultivation: 10,
+        });
+        self.courage.push(courage);
+        self.life.push(Life {
+            age: 20,
+            lifespan: 100,
+            alive: true,
+            ticks: 0,
+        });
+        self.injury.push(None);
+        self.position.push(position);
+        self.born.push(born);
+        self.ascended.push(false);
+    }
+
+    fn swap_remove(&mut self, row: usize) {
+        self.cultivation.swap_remove(row);
+        self.courage.swap_remove(row);
+        self.life.swap_remove(row);
+        self.injury.swap_remove(row);
+        self.position.swap_remove(row);
+        self.born.swap_remove(row);
+        self.ascended.swap_remove(row);
+    }
+
+    fn spawn(&mut self, rng: &mut impl Rng, config: &Config, state: &GlobalState) {
+        let spatial = &config.spatial;
+        let count = config.time.share(config.spawn_per_year as u64, state.tick);
+        self.spawned = count as usize;
+        for _ in 0..count {
+            let courage = rng.random();
+            let position = spatial.enabled.then(|| Position {
+                x: rng.random_range(0..spatial.width),
+                y: rng.random_range(0..spatial.height),
+            });
+            self.push(courage, position, state.year);
+        }
+    }
+
+    fn advance(&mut self) {
+        for (cult, life) in self.cultivation.iter_mut().zip(&mut self.life) {
+            if let Some(next_level) = cult.level.next_level()
+                && cult.cultivation >= next_level.required_cultivation()
+            {
+                cult.level = next_level;
+                life.lifespan = next_level.total_lifespan();
+            }
+        }
+    }
+
+    fn migrate(&mut self, rng: &mut impl Rng, config: &Config) {
+        let spatial = &config.spatial;
+        for position in self.position.iter_mut().flatten() {
+            if rng.random::<f64>() < config.time.rate(spatial.migration_rate) {
+                let direction = rng.random_range(0..4);
+                *position = position.step(direction, spatial.width, spatial.height);
+            }
+        }
+    }
+
+    // Pairs each cell on its own as with entities, keeping the pairs of peers by cell and level.
+    fn pair(&mut self, seed: u64, config: &Config) -> Partitions {
+        let mut cells: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for row in (0..self.len()).filter(|r| self.injury[*r].is_none()) {
+            let member = (row, self.cultivation[row].level);
+            cells.entry(self.position[row]).or_default().push(member);
+        }
+        let cells = par_map(seed, cells, |rng, _, members| {
+            pair_partition(rng, config, members)
+        });
+        let mut partitions: Partitions = BTreeMap::new();
+        for (pairs, unmatched) in cells {
+            self.unmatched += unmatched;
+            for (a, b) in pairs {
+                let level = self.cultivation[a].level;
+                if level == self.cultivation[b].level {
+                    let key = (self.position[a], level);
+                    partitions.entry(key).or_default().push((a, b));
+                }
+            }
+        }
+        partitions
+    }
+
+    // Keeps the pairs where both stand their ground or the fleer fails to escape.
+    fn filter(&mut self, seed: u64, config: &Config, partitions: Partitions) -> Partitions {
+        let columns = &*self;
+        let will_battle = |a: usize, b: usize| {
+            let win_rate = columns.cultivation[a].get_win_rate(&columns.cultivation[b]);
+            columns.courage[a] > 1.0 - win_rate
+        };
+        let keys: Vec<_> = partitions.keys().copied().collect();
+        let filtered = par_map(seed, partitions, |rng, _, candidates| {
+            let mut escapes = 0;
+            let mut kept = Vec::new();
+            for (a, b) in candidates {
+                let (fleer, chaser) = match (will_battle(a, b), will_battle(b, a)) {
+                    (false, false) => continue,
+                    (true, true) => {
+                        kept.push((a, b));
+                        continue;
+                    }
+                    (true, false) => (b, a),
+                    (false, true) => (a, b),
+                };
+                let (fleer, chaser) = (&columns.cultivation[fleer], &columns.cultivation[chaser]);
+                let rate = escape_rate(fleer, chaser, config.escape.factor);
+                if rate > 0.0 && rng.random::<f64>() < rate {
+                    escapes += 1;
+                } else {
+                    kept.push((a, b));
+                }
+            }
+            (kept, escapes)
+        });
+        let mut partitions = BTreeMap::new();
+        for (key, (kept, escapes)) in keys.into_iter().zip(filtered) {
+            self.escapes += escapes;
+            partitions.insert(key, kept);
+        }
+        partitions
+    }
+
+    // Cells and levels share no rows, so they are fought in parallel on copies and written back
+    // after.
+    fn battle(&mut self, seed: u64, config: &Config, partitions: Partitions) {
+        let columns = &*self;
+        let resolved = par_map(seed, partitions, |rng, _, pairs| {
+            let mut fighters = HashMap::new();
+            for row in pairs.iter().flat_map(|(a, b)| [*a, *b]) {
+                fighters.entry(row).or_insert_with(|| Fighter {
+                    cultivation: columns.cultivation[row].clone(),
+                    life: columns.life[row].clone(),
+                    demonic: false,
+                    gain: 0,
+                    injured: false,
+                });
+            }
+            let outcomes = resolve(rng, config, &pairs, &mut fighters);
+            (fighters, outcomes)
+        });
+        let recovery = config.injury.recovery_years * config.time.ticks_per_year;
+        for (fighters, outcomes) in resolved {
+            for (row, fighter) in fighters {
+                self.cultivation[row] = fighter.cultivation;
+                self.life[row] = fighter.life;
+                if fighter.injured {
+                    self.injury[row] = Some(recovery);
+                }
+            }
+            for outcome in outcomes {
+                match outcome {
+                    Outcome::Fled { .. } => self.escapes += 1,
+                    Outcome::Fought { killed: false, .. } => self.injuries += 1,
+                    Outcome::Fought { .. } => {}
+                }
+            }
+        }
+    }
+
+    fn cultivate(&mut self, config: &Config) {
+        let rows = self
+            .cultivation
+            .iter_mut()
+            .zip(&self.life)
+            .zip(&self.injury);
+        for ((cult, life), injury) in rows {
+            if injury.is_none() {
+                cult.cultivation += config.time.share(config.cult_per_year, life.ticks);
+            }
+        }
+    }
+
+    fn ascend(&mut self, config: &Config) {
+        if !config.ascension.enabled {
+            return;
+        }
+        let rows = self
+            .cultivation
+            .iter()
+            .zip(&mut self.life)
+            .zip(&mut self.ascended);
+        for ((cult, life), ascended) in rows {
+            if cult.level == Level::Mahayana
+                && cult.cultivation >= config.ascension.cultivation
+                && life.alive
+            {
+                life.alive = false;
+                *ascended = true;
+            }
+        }
+    }
+
+    fn age(&mut self, config: &Config) {
+        for life in &mut self.life {
+            life.tick(config.time.ticks_per_year);
+        }
+        for injury in &mut self.injury {
+            *injury = match *injury {
+                Some(ticks) if ticks > 1 => Some(ticks - 1),
+                _ => None,
+            };
+        }
+    }
+
+    fn bury(&mut self, ev_death: &mut EventWriter<DeathEvent>) {
+        let mut row = 0;
+        while row < self.len() {
+            let life = &self.life[row];
+            if life.alive {
+                row += 1;
+                continue;
+            }
+            ev_death.write(DeathEvent {
+                life: life.clone(),
+                cause: DeathCause::of(life, self.ascended[row]),
+                demonic: false,
+                level: Some(self.cultivation[row].level),
+                born: Some(self.born[row]),
+            });
+            self.swap_remove(row);
+        }
+    }
+}
+
+// One tick of the columnar world, in the order the entity systems play it.
+pub(crate) fn step(
+    mut columns: ResMut<Columns>,
+    mut rng: GlobalEntropy<WyRand>,
+    config: Res<Config>,
+    state: Res<GlobalState>,
+    mut ev_death: EventWriter<DeathEvent>,
+) {
+    let (columns, config) = (columns.as_mut(), &*config);
+    columns.spawn(&mut rng, config, &state);
+    columns.advance();
+    if config.spatial.enabled {
+        columns.migrate(&mut rng, config);
+    }
+    let pairs = columns.pair(rng.random(), config);
+    let pairs = columns.filter(rng.random(), config, pairs);
+    columns.battle(rng.random(), config, pairs);
+    columns.cultivate(config);
+    columns.ascend(config);
+    columns.age(config);
+    columns.bury(&mut ev_death);
+}
+
+pub fn columnar_plugin(app: &mut App) {
+    app.init_resource::<Columns>();
+    app.add_systems(
+        Update,
+        step.after(despawn_dead)
+            .run_if(not(ecs_backend))
+            .in_set(GamePlay::Finish),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_rand::plugin::EntropyPlugin;
+
+    #[test]
+    fn test_step() {
+        let run = || {
+            let mut app = App::new();
+            app.add_plugins(EntropyPlugin::<WyRand>::with_seed(42u64.to_le_bytes()));
+            app.add_event::<DeathEvent>();
+            app.init_resource::<Columns>();
+            app.init_resource::<GlobalState>();
+            let mut config = Config::default();
+            config.injury.enabled = true;
+            config.injury.death_rate = vec![0.5; 7];
+            app.insert_resource(config);
+            app.add_systems(Update, step);
+            for _ in 0..50 {
+                app.update();
+            }
+            let deaths = app.world().resource::<Events<DeathEvent>>().len();
+            let columns = app.world().resource::<Columns>();
+            let rows: Vec<_> = (0..columns.len())
+                .map(|r| (columns.cultivation[r].cultivation, columns.life[r].age))
+                .collect();
+            assert!(columns.life.iter().all(|l| l.alive));
+            assert_eq!(columns.born.len(), columns.len());
+            (rows, deaths, columns.injuries)
+        };
+        let first = run();
+        assert!(first.1 > 0 && first.2 > 0);
+        assert_eq!(first, run());
+    }
+}
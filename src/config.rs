@@ -1,12 +1,12 @@
 use std::fs::{self, File};
 
-use anyhow::Result;
+use anyhow::{Result, ensure};
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::level::Level;
 
-static CONFIG_DIR: &'static str = "config.json";
+static CONFIG_DIR: &str = "config.json";
 
 // Sequence in form of A_n = A_(n-1) + a * b ^ (n-1)
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -22,13 +22,497 @@ impl Sequence {
     }
 }
 
-#[derive(Debug, Resource, Serialize, Deserialize, PartialEq, Eq)]
+// Chance to flee is factor * objective win rate of the one who flees, 0 disables escaping.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct EscapeConfig {
+    pub factor: f64,
+}
+
+// Losers of a battle survive with an injury unless they roll under the death rate of their level.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct InjuryConfig {
+    pub enabled: bool,
+    pub death_rate: Vec<f64>,
+    pub cultivation_loss: f64,
+    pub lifespan_loss: u64,
+    pub recovery_years: u64,
+}
+
+impl InjuryConfig {
+    pub fn death_rate(&self, level: Level) -> f64 {
+        self.death_rate.get(level.idx()).copied().unwrap_or(1.0)
+    }
+}
+
+impl Default for InjuryConfig {
+    fn default() -> Self {
+        InjuryConfig {
+            enabled: false,
+            death_rate: vec![0.5; 7],
+            cultivation_loss: 0.1,
+            lifespan_loss: 10,
+            recovery_years: 5,
+        }
+    }
+}
+
+// Newly spawned cultivators join one of `count` sects with probability `recruit_rate`.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SectConfig {
+    pub count: usize,
+    pub recruit_rate: f64,
+    pub revenge: bool,
+}
+
+impl Default for SectConfig {
+    fn default() -> Self {
+        SectConfig {
+            count: 0,
+            recruit_rate: 0.5,
+            revenge: false,
+        }
+    }
+}
+
+// Cultivators live on a width x height grid, moving to a neighbouring cell with probability
+// `migration_rate` each year, and only meet those in the same cell.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SpatialConfig {
+    pub enabled: bool,
+    pub width: u32,
+    pub height: u32,
+    pub migration_rate: f64,
+}
+
+impl Default for SpatialConfig {
+    fn default() -> Self {
+        SpatialConfig {
+            enabled: false,
+            width: 8,
+            height: 8,
+            migration_rate: 0.1,
+        }
+    }
+}
+
+// Spiritual veins multiply the yearly cultivation gain of their holder, each held vein is
+// challenged with probability `challenge_rate` every year.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct VeinConfig {
+    pub count: usize,
+    pub multiplier: u64,
+    pub challenge_rate: f64,
+}
+
+impl Default for VeinConfig {
+    fn default() -> Self {
+        VeinConfig {
+            count: 0,
+            multiplier: 5,
+            challenge_rate: 0.5,
+        }
+    }
+}
+
+// When enabled, every cultivator takes part in a Poisson distributed number of encounters a
+// year with the mean given by their level, instead of exactly one.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct EncounterConfig {
+    pub enabled: bool,
+    pub rate: Vec<f64>,
+}
+
+impl EncounterConfig {
+    pub fn rate(&self, level: Level) -> f64 {
+        self.rate.get(level.idx()).copied().unwrap_or(1.0)
+    }
+}
+
+impl Default for EncounterConfig {
+    fn default() -> Self {
+        EncounterConfig {
+            enabled: false,
+            rate: vec![1.0; 7],
+        }
+    }
+}
+
+// A year is played in `ticks_per_year` ticks, yearly quantities are spread over them.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct TimeConfig {
+    pub ticks_per_year: u64,
+}
+
+impl TimeConfig {
+    // Integer share of a yearly amount that falls on the given tick of the year.
+    pub fn share(&self, per_year: u64, tick: u64) -> u64 {
+        let n = self.ticks_per_year;
+        per_year * (tick % n + 1) / n - per_year * (tick % n) / n
+    }
+
+    // Probability per tick of something that happens with the given probability per year.
+    pub fn rate(&self, per_year: f64) -> f64 {
+        per_year / self.ticks_per_year as f64
+    }
+}
+
+impl Default for TimeConfig {
+    fn default() -> Self {
+        TimeConfig { ticks_per_year: 1 }
+    }
+}
+
+// Mahayana cultivators reaching `cultivation` ascend and leave the world.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AscensionConfig {
+    pub enabled: bool,
+    pub cultivation: u64,
+}
+
+impl Default for AscensionConfig {
+    fn default() -> Self {
+        AscensionConfig {
+            enabled: false,
+            cultivation: 100000000,
+        }
+    }
+}
+
+// New cultivators become a disciple of a master at level index `master_level` or above with
+// probability `disciple_rate`, gaining `boost` cultivation. A master who dies leaves the
+// `inheritance` fraction of their cultivation to their living disciples.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct LineageConfig {
+    pub enabled: bool,
+    pub master_level: usize,
+    pub max_disciples: usize,
+    pub disciple_rate: f64,
+    pub boost: u64,
+    pub inheritance: f64,
+}
+
+impl Default for LineageConfig {
+    fn default() -> Self {
+        LineageConfig {
+            enabled: false,
+            master_level: 1,
+            max_disciples: 3,
+            disciple_rate: 0.1,
+            boost: 10,
+            inheritance: 0.5,
+        }
+    }
+}
+
+// The master and disciples of a slain cultivator remember the killer, seek them out with
+// probability `seek_rate` a year and face them with `courage_boost` extra courage. Needs
+// `lineage`, whose ties the grudges follow.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct VengeanceConfig {
+    pub enabled: bool,
+    pub seek_rate: f64,
+    pub courage_boost: f64,
+}
+
+impl Default for VengeanceConfig {
+    fn default() -> Self {
+        VengeanceConfig {
+            enabled: false,
+            seek_rate: 0.5,
+            courage_boost: 0.3,
+        }
+    }
+}
+
+// Cultivators judge an opponent's cultivation with a relative error of up to `noise`, and
+// each hides up to a random fraction below `concealment` of their own.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PerceptionConfig {
+    pub enabled: bool,
+    pub noise: f64,
+    pub concealment: f64,
+}
+
+impl Default for PerceptionConfig {
+    fn default() -> Self {
+        PerceptionConfig {
+            enabled: false,
+            noise: 0.3,
+            concealment: 0.5,
+        }
+    }
+}
+
+// Multi-round combat: health equals cultivation, each round a fighter strikes for a random
+// share around `damage` of their own cultivation, and a fighter below `flee_health` of their
+// health may try to escape, succeeding with `flee_factor` times their share of the health left.
+// Damage taken costs the survivor `wound` of it in cultivation.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CombatConfig {
+    pub enabled: bool,
+    pub rounds: Vec<u64>,
+    pub damage: Vec<f64>,
+    pub flee_health: f64,
+    pub flee_factor: f64,
+    pub wound: f64,
+}
+
+impl CombatConfig {
+    pub fn rounds(&self, level: Level) -> u64 {
+        self.rounds.get(level.idx()).copied().unwrap_or(1)
+    }
+
+    pub fn damage(&self, level: Level) -> f64 {
+        self.damage.get(level.idx()).copied().unwrap_or(1.0)
+    }
+}
+
+impl Default for CombatConfig {
+    fn default() -> Self {
+        CombatConfig {
+            enabled: false,
+            rounds: vec![10; 7],
+            damage: vec![0.2; 7],
+            flee_health: 0.3,
+            flee_factor: 1.0,
+            wound: 0.5,
+        }
+    }
+}
+
+// Each year a cultivator plots against the strongest cultivator around with probability
+// `rate`, gathering up to `max_size` attackers in all.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AmbushConfig {
+    pub enabled: bool,
+    pub rate: f64,
+    pub max_size: usize,
+}
+
+impl Default for AmbushConfig {
+    fn default() -> Self {
+        AmbushConfig {
+            enabled: false,
+            rate: 0.05,
+            max_size: 3,
+        }
+    }
+}
+
+// Items are found with yearly probability `find_rate` or dropped by the loser of a battle
+// with probability `drop_rate`. A lifespan pill adds `lifespan_pill` years, a cultivation pill
+// adds `cultivation_pill` of the current cultivation and a talisman lets a fleer escape with
+// probability `talisman_rate`.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ItemConfig {
+    pub enabled: bool,
+    pub find_rate: f64,
+    pub drop_rate: f64,
+    pub lifespan_pill: u64,
+    pub cultivation_pill: f64,
+    pub talisman_rate: f64,
+}
+
+impl Default for ItemConfig {
+    fn default() -> Self {
+        ItemConfig {
+            enabled: false,
+            find_rate: 0.05,
+            drop_rate: 0.3,
+            lifespan_pill: 50,
+            cultivation_pill: 0.1,
+            talisman_rate: 0.9,
+        }
+    }
+}
+
+// Each year a cultivator with courage below `courage` and at least `headroom` of their
+// lifespan ahead withdraws with probability `rate` for `years`, cultivating at `growth` times
+// the usual pace.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SeclusionConfig {
+    pub enabled: bool,
+    pub rate: f64,
+    pub courage: f64,
+    pub headroom: f64,
+    pub years: u64,
+    pub growth: f64,
+}
+
+impl Default for SeclusionConfig {
+    fn default() -> Self {
+        SeclusionConfig {
+            enabled: false,
+            rate: 0.1,
+            courage: 0.3,
+            headroom: 0.5,
+            years: 10,
+            growth: 2.0,
+        }
+    }
+}
+
+// Demonic cultivators absorb `absorption` of a defeated foe instead of a tenth, but live
+// `lifespan_penalty` shorter, face `infamy` extra courage from orthodox opponents and fail a
+// breakthrough with probability `deviation`, losing a tenth of their cultivation. A share
+// `spawn_rate` is born demonic, others fall after `conversion_kills` kills.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DemonicConfig {
+    pub enabled: bool,
+    pub spawn_rate: f64,
+    pub conversion_kills: usize,
+    pub absorption: f64,
+    pub lifespan_penalty: f64,
+    pub infamy: f64,
+    pub deviation: f64,
+}
+
+impl Default for DemonicConfig {
+    fn default() -> Self {
+        DemonicConfig {
+            enabled: false,
+            spawn_rate: 0.05,
+            conversion_kills: 5,
+            absorption: 0.3,
+            lifespan_penalty: 0.2,
+            infamy: 0.2,
+            deviation: 0.3,
+        }
+    }
+}
+
+// Demographic tables, grouping cultivators into cohorts by `cohort_years` of birth and
+// into bands of `age_band` years of age.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DemographyConfig {
+    pub enabled: bool,
+    pub cohort_years: u64,
+    pub age_band: u64,
+}
+
+impl Default for DemographyConfig {
+    fn default() -> Self {
+        DemographyConfig {
+            enabled: false,
+            cohort_years: 100,
+            age_band: 10,
+        }
+    }
+}
+
+// Periodic report, listing the top `leaderboard` cultivators of each ranking and
+// histograms of `bins` equal bins.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ReportConfig {
+    pub leaderboard: usize,
+    pub bins: usize,
+}
+
+impl Default for ReportConfig {
+    fn default() -> Self {
+        ReportConfig {
+            leaderboard: 5,
+            bins: 10,
+        }
+    }
+}
+
+// Reports are also written as JSON lines into `dir` when enabled.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ExportConfig {
+    pub enabled: bool,
+    pub dir: String,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        ExportConfig {
+            enabled: false,
+            dir: "export".to_string(),
+        }
+    }
+}
+
+// Where cultivators are kept: one entity each, or dense columns for very long runs of the core
+// rules. The columnar backend refuses configs enabling any other rule, and draws in its own
+// order, so a seed reproduces a columnar run but not an entity one, see `columnar`.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    #[default]
+    Ecs,
+    Columnar,
+}
+
+#[derive(Debug, Resource, Serialize, Deserialize, PartialEq)]
 pub struct Config {
     pub cult_default: u64,
     pub cult_per_year: u64,
     pub lifespan: Sequence,
     pub lvup: Sequence,
     pub spawn_per_year: usize,
+    // Seed of the global random stream, a run is reproducible when it is set.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub backend: Backend,
+    #[serde(default)]
+    pub escape: EscapeConfig,
+    #[serde(default)]
+    pub injury: InjuryConfig,
+    #[serde(default)]
+    pub sect: SectConfig,
+    #[serde(default)]
+    pub spatial: SpatialConfig,
+    #[serde(default)]
+    pub vein: VeinConfig,
+    #[serde(default)]
+    pub encounter: EncounterConfig,
+    #[serde(default)]
+    pub time: TimeConfig,
+    #[serde(default)]
+    pub ascension: AscensionConfig,
+    #[serde(default)]
+    pub lineage: LineageConfig,
+    #[serde(default)]
+    pub vengeance: VengeanceConfig,
+    #[serde(default)]
+    pub perception: PerceptionConfig,
+    #[serde(default)]
+    pub combat: CombatConfig,
+    #[serde(default)]
+    pub ambush: AmbushConfig,
+    #[serde(default)]
+    pub item: ItemConfig,
+    #[serde(default)]
+    pub seclusion: SeclusionConfig,
+    #[serde(default)]
+    pub demonic: DemonicConfig,
+    #[serde(default)]
+    pub report: ReportConfig,
+    #[serde(default)]
+    pub export: ExportConfig,
+    #[serde(default)]
+    pub demography: DemographyConfig,
 }
 
 impl Default for Config {
@@ -47,13 +531,84 @@ impl Default for Config {
                 b: 10,
             },
             spawn_per_year: 100,
+            seed: None,
+            backend: Backend::Ecs,
+            escape: EscapeConfig::default(),
+            injury: InjuryConfig::default(),
+            sect: SectConfig::default(),
+            spatial: SpatialConfig::default(),
+            vein: VeinConfig::default(),
+            encounter: EncounterConfig::default(),
+            time: TimeConfig::default(),
+            ascension: AscensionConfig::default(),
+            lineage: LineageConfig::default(),
+            vengeance: VengeanceConfig::default(),
+            perception: PerceptionConfig::default(),
+            combat: CombatConfig::default(),
+            ambush: AmbushConfig::default(),
+            item: ItemConfig::default(),
+            seclusion: SeclusionConfig::default(),
+            demonic: DemonicConfig::default(),
+            report: ReportConfig::default(),
+            export: ExportConfig::default(),
+            demography: DemographyConfig::default(),
         }
     }
 }
 
+impl Config {
+    // Rejects values the simulation would divide by zero or draw from an empty range with.
+    fn validate(&self) -> Result<()> {
+        ensure!(
+            self.time.ticks_per_year > 0,
+            "ticks_per_year must be at least 1"
+        );
+        let spatial = &self.spatial;
+        ensure!(
+            spatial.width > 0 && spatial.height > 0,
+            "spatial grid must be at least 1x1"
+        );
+        let demography = &self.demography;
+        ensure!(
+            demography.age_band > 0 && demography.cohort_years > 0,
+            "demography age_band and cohort_years must be at least 1"
+        );
+        // Grudges are taken up by masters and disciples only, sect mates avenge through
+        // `sect.revenge`.
+        ensure!(
+            !self.vengeance.enabled || self.lineage.enabled,
+            "vengeance needs lineage enabled, only masters and disciples avenge the slain"
+        );
+        if self.backend == Backend::Columnar {
+            let unsupported = [
+                ("sect", self.sect.count > 0),
+                ("vein", self.vein.count > 0),
+                ("lineage", self.lineage.enabled),
+                ("vengeance", self.vengeance.enabled),
+                ("perception", self.perception.enabled),
+                ("ambush", self.ambush.enabled),
+                ("item", self.item.enabled),
+                ("seclusion", self.seclusion.enabled),
+                ("demonic", self.demonic.enabled),
+                ("demography", self.demography.enabled),
+            ];
+            for (name, enabled) in unsupported {
+                ensure!(
+                    !enabled,
+                    "{} is not played by the columnar backend, disable it or use the ecs backend",
+                    name
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
 fn read_config(path: &str) -> Result<Config> {
     let file = File::open(path)?;
-    Ok(serde_json::from_reader(file)?)
+    let config: Config = serde_json::from_reader(file)?;
+    config.validate()?;
+    Ok(config)
 }
 
 fn write_config(path: &str, config: &Config) -> Result<()> {
@@ -62,7 +617,7 @@ fn write_config(path: &str, config: &Config) -> Result<()> {
     Ok(())
 }
 
-fn read_config_system(mut config: ResMut<Config>) {
+pub fn read_config_system(mut config: ResMut<Config>) {
     if let Ok(false) = fs::exists(CONFIG_DIR) {
         let _ = write_config(CONFIG_DIR, &Config::default());
     }
@@ -99,6 +654,15 @@ mod tests {
         assert_eq!(seq.diff(3), 9000);
     }
 
+    #[test]
+    fn test_time_share() {
+        let time = TimeConfig { ticks_per_year: 12 };
+        let shares: Vec<u64> = (0..12).map(|t| time.share(5, t)).collect();
+        assert_eq!(shares.iter().sum::<u64>(), 5);
+        assert!(shares.iter().all(|s| *s <= 1));
+        assert_eq!(TimeConfig::default().share(5, 7), 5);
+    }
+
     #[test]
     fn test_read_write_config() {
         let config = Config::default();
@@ -110,4 +674,30 @@ mod tests {
 
         assert_eq!(config, read_config);
     }
+
+    #[test]
+    fn test_validate() {
+        assert!(Config::default().validate().is_ok());
+        let mut config = Config::default();
+        config.spatial.height = 0;
+        assert!(config.validate().is_err());
+        let mut config = Config::default();
+        config.time.ticks_per_year = 0;
+        assert!(config.validate().is_err());
+        let mut config = Config::default();
+        config.demography.age_band = 0;
+        assert!(config.validate().is_err());
+        let mut config = Config::default();
+        config.vengeance.enabled = true;
+        assert!(config.validate().is_err());
+        config.lineage.enabled = true;
+        assert!(config.validate().is_ok());
+        let mut config = Config {
+            backend: Backend::Columnar,
+            ..default()
+        };
+        assert!(config.validate().is_ok());
+        config.sect.count = 3;
+        assert!(config.validate().is_err());
+    }
 }
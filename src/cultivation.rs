@@ -1,29 +1,110 @@
+use crate::biography::Biography;
+use crate::config::Config;
+use crate::demonic::{Demonic, shorten};
+use crate::injury::Injury;
+use crate::item::{self, Inventory};
 use crate::level::Level;
 use crate::life::Life;
-use crate::system::GamePlay;
+use crate::seclusion::Secluded;
+use crate::system::{GamePlay, GlobalState};
+use crate::vein::VeinHolder;
 use bevy::prelude::*;
+use bevy_prng::WyRand;
+use bevy_rand::global::GlobalEntropy;
+use rand::Rng;
 
-#[derive(Component, Debug)]
+// Circumstances that change the pace of cultivation.
+type Boosts = (Has<VeinHolder>, Has<Secluded>);
+
+#[derive(Component, Debug, Clone)]
 pub struct Cultivation {
     pub level: Level,
     pub cultivation: u64,
 }
 
+// Emitted when a cultivator breaks through into `level`.
+#[derive(Event, Debug)]
+pub struct BreakthroughEvent {
+    pub entity: Entity,
+    pub level: Level,
+}
+
+// Marks a cultivator who left the world by ascending rather than dying.
+#[derive(Component, Debug)]
+pub struct Ascended;
+
 impl Cultivation {
-    fn try_advance(query: Query<(&mut Cultivation, &mut Life)>) {
-        for (mut cult, mut life) in query {
+    pub(crate) fn try_advance(
+        query: Query<(Entity, &mut Cultivation, &mut Life, Has<Demonic>)>,
+        mut biographies: Query<&mut Biography>,
+        inventories: Query<&Inventory>,
+        mut rng: GlobalEntropy<WyRand>,
+        config: Res<Config>,
+        state: Res<GlobalState>,
+        mut ev_breakthrough: EventWriter<BreakthroughEvent>,
+    ) {
+        let demonic = &config.demonic;
+        for (entity, mut cult, mut life, is_demonic) in query {
             if let Some(next_level) = cult.level.next_level()
                 && cult.cultivation >= next_level.required_cultivation()
             {
+                // Demonic arts are unstable, a failed breakthrough scatters part of the qi.
+                if is_demonic && rng.random::<f64>() < demonic.deviation {
+                    cult.cultivation -= cult.cultivation / 10;
+                    continue;
+                }
                 cult.level = next_level;
-                life.lifespan = next_level.total_lifespan();
+                // Years granted by pills come on top of what the new level grants.
+                let extra_years = inventories.get(entity).map_or(0, |i| i.extra_years);
+                life.lifespan = next_level.total_lifespan() + extra_years;
+                if let Ok(mut biography) = biographies.get_mut(entity) {
+                    biography.breakthroughs.push((next_level, state.year));
+                }
+                if is_demonic {
+                    life.lifespan = shorten(demonic, life.lifespan);
+                }
+                ev_breakthrough.write(BreakthroughEvent {
+                    entity,
+                    level: next_level,
+                });
             }
         }
     }
 
-    fn increase_cultivation(query: Query<&mut Cultivation>) {
-        for mut cult in query {
-            cult.cultivation += 1;
+    fn increase_cultivation(
+        query: Query<(&mut Cultivation, &Life, Boosts, Option<&mut Biography>), Without<Injury>>,
+        config: Res<Config>,
+    ) {
+        for (mut cult, life, (holder, secluded), biography) in query {
+            let multiplier = if holder { config.vein.multiplier } else { 1 };
+            let mut per_year = config.cult_per_year * multiplier;
+            if secluded {
+                per_year = (per_year as f64 * config.seclusion.growth).round() as u64;
+            }
+            let gain = config.time.share(per_year, life.ticks);
+            cult.cultivation += gain;
+            if let Some(mut biography) = biography {
+                biography.time_gain += gain;
+            }
+        }
+    }
+
+    pub(crate) fn try_ascend(
+        mut commands: Commands,
+        query: Query<(Entity, &Cultivation, &mut Life)>,
+        config: Res<Config>,
+    ) {
+        if !config.ascension.enabled {
+            return;
+        }
+        for (entity, cult, mut life) in query {
+            if cult.level == Level::Mahayana
+                && cult.cultivation >= config.ascension.cultivation
+                && life.alive
+            {
+                life.alive = false;
+                commands.entity(entity).insert(Ascended);
+            }
         }
     }
 
@@ -33,11 +114,21 @@ impl Cultivation {
 }
 
 pub fn cultivation_plugin(app: &mut App) {
+    app.add_event::<BreakthroughEvent>();
     app.add_systems(
         Update,
         (
-            Cultivation::try_advance.in_set(GamePlay::Spawn),
-            Cultivation::increase_cultivation.in_set(GamePlay::Finish),
+            Cultivation::try_advance
+                .after(item::find_items)
+                .in_set(GamePlay::Spawn),
+            Cultivation::increase_cultivation
+                .after(item::consume_items)
+                .before(Life::increase_age)
+                .in_set(GamePlay::Finish),
+            Cultivation::try_ascend
+                .after(Cultivation::increase_cultivation)
+                .before(Life::increase_age)
+                .in_set(GamePlay::Finish),
         ),
     );
 }
@@ -64,4 +155,46 @@ mod tests {
         };
         assert_eq!(cult3.get_win_rate(&cult1), 200.0 / 300.0);
     }
+
+    #[test]
+    fn test_try_ascend() {
+        let mut app = App::new();
+        let mut config = Config::default();
+        config.ascension.enabled = true;
+        config.ascension.cultivation = 1000;
+        app.insert_resource(config);
+        app.add_systems(Update, Cultivation::try_ascend);
+
+        let life = Life {
+            age: 100,
+            lifespan: 1000,
+            alive: true,
+            ticks: 0,
+        };
+        let ascended = app
+            .world_mut()
+            .spawn((
+                Cultivation {
+                    level: Level::Mahayana,
+                    cultivation: 1000,
+                },
+                life.clone(),
+            ))
+            .id();
+        let mortal = app
+            .world_mut()
+            .spawn((
+                Cultivation {
+                    level: Level::BodyIntegration,
+                    cultivation: 1000,
+                },
+                life,
+            ))
+            .id();
+        app.update();
+
+        assert!(!app.world().get::<Life>(ascended).unwrap().alive);
+        assert!(app.world().get::<Ascended>(ascended).is_some());
+        assert!(app.world().get::<Life>(mortal).unwrap().alive);
+    }
 }
@@ -0,0 +1,379 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::time::common_conditions::on_timer;
+use serde::Serialize;
+
+use crate::biography::Biography;
+use crate::config::Config;
+use crate::cultivation::Cultivation;
+use crate::export::Exporter;
+use crate::level::Level;
+use crate::life::Life;
+use crate::spawn::{DeathCause, DeathEvent, despawn_dead};
+use crate::system::{GamePlay, ecs_backend};
+
+// Deaths and censored exits (ascensions, and the living when tables are drawn) at one age.
+#[derive(Debug, Default, Clone, Copy)]
+struct Exits {
+    deaths: usize,
+    censored: usize,
+}
+
+#[derive(Debug, Default)]
+struct Risk {
+    exposure: f64,
+    battle: usize,
+    old_age: usize,
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct Demography {
+    // Exits by cohort and age.
+    cohorts: BTreeMap<u64, BTreeMap<u64, Exits>>,
+    // Years lived and deaths by level and age band.
+    risks: BTreeMap<(Level, u64), Risk>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct SurvivalRow {
+    cohort: u64,
+    age: u64,
+    at_risk: usize,
+    deaths: usize,
+    survival: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct LifeTableRow {
+    cohort: u64,
+    age: u64,
+    alive: usize,
+    deaths: usize,
+    censored: usize,
+    death_rate: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct HazardRow {
+    level: &'static str,
+    age: u64,
+    exposure: f64,
+    battle: usize,
+    old_age: usize,
+    battle_hazard: f64,
+    old_age_hazard: f64,
+}
+
+// Kaplan–Meier estimate over the exits of one cohort.
+fn survival(cohort: u64, exits: &BTreeMap<u64, Exits>) -> Vec<SurvivalRow> {
+    let mut at_risk: usize = exits.values().map(|e| e.deaths + e.censored).sum();
+    let mut survival = 1.0;
+    let mut rows = Vec::with_capacity(exits.len());
+    for (age, e) in exits {
+        survival *= 1.0 - e.deaths as f64 / at_risk as f64;
+        rows.push(SurvivalRow {
+            cohort,
+            age: *age,
+            at_risk,
+            deaths: e.deaths,
+            survival,
+        });
+        at_risk -= e.deaths + e.censored;
+    }
+    rows
+}
+
+fn life_table(cohort: u64, band: u64, exits: &BTreeMap<u64, Exits>) -> Vec<LifeTableRow> {
+    let mut alive: usize = exits.values().map(|e| e.deaths + e.censored).sum();
+    let mut bands: BTreeMap<u64, Exits> = BTreeMap::new();
+    for (age, e) in exits {
+        let b = bands.entry(age / band * band).or_default();
+        b.deaths += e.deaths;
+        b.censored += e.censored;
+    }
+    let mut rows = Vec::with_capacity(bands.len());
+    for (age, e) in bands {
+        rows.push(LifeTableRow {
+            cohort,
+            age,
+            alive,
+            deaths: e.deaths,
+            censored: e.censored,
+            death_rate: e.deaths as f64 / alive as f64,
+        });
+        alive -= e.deaths + e.censored;
+    }
+    rows
+}
+
+// Deaths per year lived in each level and age band, 0 where no year was lived.
+fn hazards(risks: &BTreeMap<(Level, u64), Risk>) -> impl Iterator<Item = HazardRow> {
+    risks.iter().map(|((level, age), risk)| {
+        let hazard = |deaths: usize| {
+            if risk.exposure > 0.0 {
+                deaths as f64 / risk.exposure
+            } else {
+                0.0
+            }
+        };
+        HazardRow {
+            level: level.name(),
+            age: *age,
+            exposure: risk.exposure,
+            battle: risk.battle,
+            old_age: risk.old_age,
+            battle_hazard: hazard(risk.battle),
+            old_age_hazard: hazard(risk.old_age),
+        }
+    })
+}
+
+// The age a cultivator lived their last tick at, which their exposure was recorded under. The
+// dead are reported after aging, and a tick leaving `ticks` at 0 was a birthday.
+fn lived_age(life: &Life) -> u64 {
+    if life.ticks == 0 {
+        life.age.saturating_sub(1)
+    } else {
+        life.age
+    }
+}
+
+fn record_deaths(
+    mut ev_death: EventReader<DeathEvent>,
+    config: Res<Config>,
+    mut demography: ResMut<Demography>,
+) {
+    let demo = &config.demography;
+    if !demo.enabled {
+        return;
+    }
+    for ev in ev_death.read() {
+        let age = ev.life.age;
+        if let Some(level) = ev.level {
+            let band = lived_age(&ev.life) / demo.age_band * demo.age_band;
+            let risk = demography.risks.entry((level, band)).or_default();
+            match ev.cause {
+                DeathCause::Battle => risk.battle += 1,
+                DeathCause::OldAge => risk.old_age += 1,
+                DeathCause::Ascension => {}
+            }
+        }
+        let Some(born) = ev.born else {
+            continue;
+        };
+        let cohort = born / demo.cohort_years * demo.cohort_years;
+        let exits = demography.cohorts.entry(cohort).or_default();
+        let exit = exits.entry(age).or_default();
+        // Ascended cultivators leave the world without dying.
+        if ev.cause == DeathCause::Ascension {
+            exit.censored += 1;
+        } else {
+            exit.deaths += 1;
+        }
+    }
+}
+
+fn record_exposure(
+    query: Query<(&Life, &Cultivation)>,
+    config: Res<Config>,
+    mut demography: ResMut<Demography>,
+) {
+    let demo = &config.demography;
+    if !demo.enabled {
+        return;
+    }
+    let tick = 1.0 / config.time.ticks_per_year as f64;
+    for (life, cult) in query {
+        let band = life.age / demo.age_band * demo.age_band;
+        demography
+            .risks
+            .entry((cult.level, band))
+            .or_default()
+            .exposure += tick;
+    }
+}
+
+pub(crate) fn export_demography(
+    query: Query<(&Life, &Biography)>,
+    config: Res<Config>,
+    demography: Res<Demography>,
+    exporter: Res<Exporter>,
+) {
+    let demo = &config.demography;
+    if !demo.enabled || !config.export.enabled {
+        return;
+    }
+    // The living are censored at their current age.
+    let mut cohorts = demography.cohorts.clone();
+    for (life, biography) in query.iter().filter(|(l, _)| l.alive) {
+        let cohort = biography.born / demo.cohort_years * demo.cohort_years;
+        let exits = cohorts.entry(cohort).or_default();
+        exits.entry(life.age).or_default().censored += 1;
+    }
+    let curves = cohorts.iter().flat_map(|(c, exits)| survival(*c, exits));
+    exporter.replace(&config.export, "survival", curves);
+    let tables = cohorts
+        .iter()
+        .flat_map(|(c, exits)| life_table(*c, demo.age_band, exits));
+    exporter.replace(&config.export, "life_table", tables);
+    exporter.replace(&config.export, "hazard", hazards(&demography.risks));
+}
+
+// Exposure is read off the living entities, so the tables are only kept by the entity backend.
+pub fn demography_plugin(app: &mut App) {
+    app.init_resource::<Demography>();
+    app.add_systems(
+        Update,
+        (
+            record_deaths.in_set(GamePlay::Spawn),
+            record_exposure
+                .after(Cultivation::try_ascend)
+                .before(Life::increase_age)
+                .in_set(GamePlay::Finish),
+            export_demography
+                .after(despawn_dead)
+                .run_if(on_timer(Duration::from_secs(3)))
+                .in_set(GamePlay::Finish),
+        )
+            .run_if(ecs_backend),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_survival() {
+        // Deaths at 30 and 50, one cultivator censored at 40, one still alive at 60.
+        let exits = BTreeMap::from([
+            (
+                30,
+                Exits {
+                    deaths: 1,
+                    censored: 0,
+                },
+            ),
+            (
+                40,
+                Exits {
+                    deaths: 0,
+                    censored: 1,
+                },
+            ),
+            (
+                50,
+                Exits {
+                    deaths: 1,
+                    censored: 0,
+                },
+            ),
+            (
+                60,
+                Exits {
+                    deaths: 0,
+                    censored: 1,
+                },
+            ),
+        ]);
+        let curve = survival(0, &exits);
+        let at_risk: Vec<_> = curve.iter().map(|r| r.at_risk).collect();
+        assert_eq!(at_risk, vec![4, 3, 2, 1]);
+        assert_eq!(curve[0].survival, 0.75);
+        assert_eq!(curve[2].survival, 0.375);
+        assert_eq!(curve[3].survival, 0.375);
+
+        let table = life_table(0, 20, &exits);
+        assert_eq!(table.len(), 3);
+        assert_eq!((table[0].age, table[0].alive, table[0].deaths), (20, 4, 1));
+        assert_eq!((table[1].age, table[1].alive, table[1].deaths), (40, 3, 1));
+        assert_eq!(
+            (table[2].age, table[2].alive, table[2].censored),
+            (60, 1, 1)
+        );
+    }
+
+    #[test]
+    fn test_record_deaths() {
+        let mut app = App::new();
+        app.add_event::<DeathEvent>();
+        app.init_resource::<Demography>();
+        let mut config = Config::default();
+        config.demography.enabled = true;
+        app.insert_resource(config);
+        app.add_systems(Update, record_deaths);
+
+        let death = |age, cause, born| DeathEvent {
+            life: Life {
+                age,
+                lifespan: 100,
+                alive: false,
+                ticks: 0,
+            },
+            cause,
+            demonic: false,
+            level: Some(Level::Foundation),
+            born,
+        };
+        app.world_mut()
+            .send_event(death(35, DeathCause::Battle, Some(120)));
+        app.world_mut()
+            .send_event(death(38, DeathCause::OldAge, Some(150)));
+        app.world_mut()
+            .send_event(death(38, DeathCause::Ascension, None));
+        app.update();
+
+        let demography = app.world().resource::<Demography>();
+        let risk = &demography.risks[&(Level::Foundation, 30)];
+        assert_eq!((risk.battle, risk.old_age), (1, 1));
+        let exits = &demography.cohorts[&100];
+        assert_eq!((exits[&35].deaths, exits[&38].deaths), (1, 1));
+        assert_eq!(demography.cohorts.len(), 1);
+    }
+
+    #[test]
+    fn test_old_age_hazard() {
+        let mut app = App::new();
+        app.add_event::<DeathEvent>();
+        app.init_resource::<Demography>();
+        let mut config = Config::default();
+        config.demography.enabled = true;
+        config.time.ticks_per_year = 2;
+        app.insert_resource(config);
+        app.add_systems(
+            Update,
+            (
+                record_exposure,
+                Life::increase_age,
+                despawn_dead,
+                record_deaths,
+            )
+                .chain(),
+        );
+        app.world_mut().spawn((
+            Life {
+                age: 95,
+                lifespan: 100,
+                alive: true,
+                ticks: 0,
+            },
+            Cultivation {
+                level: Level::Foundation,
+                cultivation: 10,
+            },
+        ));
+        for _ in 0..12 {
+            app.update();
+        }
+
+        // Five years lived in the band of 90, and the death at 100 filed under it.
+        let demography = app.world().resource::<Demography>();
+        let rows: Vec<_> = hazards(&demography.risks).collect();
+        assert_eq!(rows.len(), 1);
+        assert_eq!((rows[0].age, rows[0].old_age), (90, 1));
+        assert_eq!(rows[0].exposure, 5.0);
+        assert_eq!(rows[0].old_age_hazard, 0.2);
+    }
+}
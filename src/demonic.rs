@@ -0,0 +1,143 @@
+use bevy::prelude::*;
+use bevy_prng::WyRand;
+use bevy_rand::global::GlobalEntropy;
+use rand::Rng;
+
+use itertools::Itertools;
+
+use crate::battle::BattleEvent;
+use crate::biography::{self, Kills};
+use crate::config::{Config, DemonicConfig};
+use crate::cultivation::Cultivation;
+use crate::life::Life;
+use crate::perception;
+use crate::system::GamePlay;
+
+// A cultivator of the demonic path, orthodox opponents fight them with `infamy` extra courage.
+#[derive(Component, Debug)]
+pub struct Demonic {
+    pub infamy: f64,
+}
+
+// Cultivation the winner gains from a defeated opponent.
+pub(crate) fn absorb(config: &DemonicConfig, demonic: bool, cultivation: u64) -> u64 {
+    if demonic {
+        (cultivation as f64 * config.absorption) as u64
+    } else {
+        cultivation / 10
+    }
+}
+
+pub(crate) fn shorten(config: &DemonicConfig, lifespan: u64) -> u64 {
+    (lifespan as f64 * (1.0 - config.lifespan_penalty)) as u64
+}
+
+pub(crate) fn corrupt(
+    mut commands: Commands,
+    mut rng: GlobalEntropy<WyRand>,
+    config: Res<Config>,
+    query: Query<(Entity, &mut Life), Added<Cultivation>>,
+) {
+    let demonic = &config.demonic;
+    if !demonic.enabled {
+        return;
+    }
+    for (entity, mut life) in query {
+        if rng.random::<f64>() < demonic.spawn_rate {
+            life.lifespan = shorten(demonic, life.lifespan);
+            commands.entity(entity).insert(Demonic {
+                infamy: demonic.infamy,
+            });
+        }
+    }
+}
+
+// Enough blood on their hands turns an orthodox cultivator to the demonic path.
+pub(crate) fn convert(
+    mut commands: Commands,
+    config: Res<Config>,
+    mut ev_battle: EventReader<BattleEvent>,
+    mut query: Query<(&Kills, &mut Life), Without<Demonic>>,
+) {
+    let demonic = &config.demonic;
+    let killers = ev_battle.read().filter(|ev| ev.killed).map(|ev| ev.winner);
+    for killer in killers.unique() {
+        let Ok((kills, mut life)) = query.get_mut(killer) else {
+            continue;
+        };
+        if demonic.enabled && kills.count() >= demonic.conversion_kills {
+            life.lifespan = shorten(demonic, life.lifespan).max(life.age + 1);
+            commands.entity(killer).insert(Demonic {
+                infamy: demonic.infamy,
+            });
+        }
+    }
+}
+
+pub fn demonic_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            corrupt.after(perception::conceal).in_set(GamePlay::Spawn),
+            convert
+                .after(biography::record_kills)
+                .in_set(GamePlay::Battle),
+        ),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::Level;
+
+    #[test]
+    fn test_convert() {
+        let mut app = App::new();
+        app.add_event::<BattleEvent>();
+        let mut config = Config::default();
+        config.demonic.enabled = true;
+        config.demonic.conversion_kills = 2;
+        app.insert_resource(config);
+        app.add_systems(Update, (biography::record_kills, convert).chain());
+
+        let killer = app
+            .world_mut()
+            .spawn((
+                Kills::default(),
+                Life {
+                    age: 50,
+                    lifespan: 100,
+                    alive: true,
+                    ticks: 0,
+                },
+            ))
+            .id();
+        let victim = app
+            .world_mut()
+            .spawn(Cultivation {
+                level: Level::Foundation,
+                cultivation: 10,
+            })
+            .id();
+        for _ in 0..2 {
+            app.world_mut().send_event(BattleEvent {
+                winner: killer,
+                loser: victim,
+                killed: true,
+            });
+        }
+        app.update();
+
+        assert_eq!(app.world().get::<Kills>(killer).unwrap().count(), 2);
+        assert!(app.world().get::<Demonic>(killer).is_some());
+        assert_eq!(app.world().get::<Life>(killer).unwrap().lifespan, 80);
+    }
+
+    #[test]
+    fn test_absorb() {
+        let config = DemonicConfig::default();
+        assert_eq!(absorb(&config, false, 100), 10);
+        assert_eq!(absorb(&config, true, 100), 30);
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::Result;
+use bevy::prelude::*;
+use serde::Serialize;
+
+use crate::config::ExportConfig;
+
+// Writes reports as JSON lines into `<dir>/<name>.jsonl`. Series are appended to, starting
+// over on their first write of a run, while snapshots replace the whole file every time.
+#[derive(Resource, Default)]
+pub struct Exporter {
+    started: HashSet<&'static str>,
+}
+
+impl Exporter {
+    pub fn write<T: Serialize>(
+        &mut self,
+        config: &ExportConfig,
+        name: &'static str,
+        records: impl IntoIterator<Item = T>,
+    ) {
+        let fresh = self.started.insert(name);
+        Self::export(config, name, records, fresh);
+    }
+
+    pub fn replace<T: Serialize>(
+        &self,
+        config: &ExportConfig,
+        name: &'static str,
+        records: impl IntoIterator<Item = T>,
+    ) {
+        Self::export(config, name, records, true);
+    }
+
+    fn export<T: Serialize>(
+        config: &ExportConfig,
+        name: &'static str,
+        records: impl IntoIterator<Item = T>,
+        fresh: bool,
+    ) {
+        if !config.enabled {
+            return;
+        }
+        if let Err(e) = Self::try_export(config, name, records, fresh) {
+            warn!("Failed to export {} to {}, error: {}", name, config.dir, e);
+        }
+    }
+
+    fn try_export<T: Serialize>(
+        config: &ExportConfig,
+        name: &'static str,
+        records: impl IntoIterator<Item = T>,
+        fresh: bool,
+    ) -> Result<()> {
+        fs::create_dir_all(&config.dir)?;
+        let path = Path::new(&config.dir).join(format!("{name}.jsonl"));
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(fresh)
+            .append(!fresh)
+            .open(path)?;
+        let mut writer = BufWriter::new(file);
+        for record in records {
+            serde_json::to_writer(&mut writer, &record)?;
+            writeln!(writer)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+pub fn export_plugin(app: &mut App) {
+    app.init_resource::<Exporter>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exporter() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ExportConfig {
+            enabled: true,
+            dir: dir.path().to_str().unwrap().to_string(),
+        };
+        let path = dir.path().join("numbers.jsonl");
+
+        let mut exporter = Exporter::default();
+        exporter.write(&config, "numbers", [1, 2]);
+        exporter.write(&config, "numbers", [3]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "1\n2\n3\n");
+
+        // A new run starts the file over.
+        Exporter::default().write(&config, "numbers", [4]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "4\n");
+
+        exporter.replace(&config, "numbers", [5]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "5\n");
+    }
+}
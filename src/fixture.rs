@@ -0,0 +1,167 @@
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+use bevy_prng::WyRand;
+use bevy_rand::plugin::EntropyPlugin;
+use rand::{Rng, SeedableRng};
+
+use crate::battle::{self, BattleEvent, BattlePair, Courage, EscapeEvent, MisjudgeEvent};
+use crate::config::Config;
+use crate::cultivation::{BreakthroughEvent, Cultivation};
+use crate::level::Level;
+use crate::life::Life;
+use crate::spawn::{DeathEvent, despawn_dead};
+use crate::stat::{XiuxianStatistics, update_stats};
+use crate::system::GlobalState;
+
+const SEED: u64 = 42;
+
+// The systems of a tick that are benchmarked on their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Pair,
+    FilterBattle,
+    Battle,
+    TryAdvance,
+    DespawnDead,
+    UpdateStats,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 6] = [
+        Stage::Pair,
+        Stage::FilterBattle,
+        Stage::Battle,
+        Stage::TryAdvance,
+        Stage::DespawnDead,
+        Stage::UpdateStats,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::Pair => "pair",
+            Stage::FilterBattle => "filter_battle",
+            Stage::Battle => "battle",
+            Stage::TryAdvance => "try_advance",
+            Stage::DespawnDead => "despawn_dead",
+            Stage::UpdateStats => "update_stats",
+        }
+    }
+
+    fn schedule(&self) -> Schedule {
+        let mut schedule = Schedule::default();
+        match self {
+            Stage::Pair => schedule.add_systems(battle::pair),
+            Stage::FilterBattle => schedule.add_systems(battle::filter_battle),
+            Stage::Battle => schedule.add_systems(battle::battle),
+            Stage::TryAdvance => schedule.add_systems(Cultivation::try_advance),
+            Stage::DespawnDead => schedule.add_systems(despawn_dead),
+            Stage::UpdateStats => schedule.add_systems(update_stats),
+        };
+        schedule
+    }
+}
+
+// A world of `population` cultivators drawn from a fixed seed, with the stages before `stage`
+// already played, so the stage sees the same input on every run and every commit.
+pub struct Fixture {
+    app: App,
+    schedule: Schedule,
+}
+
+impl Fixture {
+    pub fn new(stage: Stage, population: usize) -> Self {
+        let mut app = App::new();
+        app.add_plugins(EntropyPlugin::<WyRand>::with_seed(SEED.to_le_bytes()));
+        app.add_event::<BattleEvent>();
+        app.add_event::<EscapeEvent>();
+        app.add_event::<MisjudgeEvent>();
+        app.add_event::<BreakthroughEvent>();
+        app.add_event::<DeathEvent>();
+        app.init_resource::<BattlePair>();
+        app.init_resource::<GlobalState>();
+        app.init_resource::<XiuxianStatistics>();
+        app.insert_resource(Config::default());
+        // Plugins only take effect once the app is finished.
+        app.finish();
+        app.cleanup();
+        populate(app.world_mut(), population);
+
+        let world = app.world_mut();
+        if matches!(stage, Stage::FilterBattle | Stage::Battle) {
+            world.run_system_once(battle::pair).unwrap();
+        }
+        if stage == Stage::Battle {
+            world.run_system_once(battle::filter_battle).unwrap();
+        }
+        let mut schedule = stage.schedule();
+        schedule.initialize(world).unwrap();
+        Fixture { app, schedule }
+    }
+
+    pub fn run(&mut self) {
+        self.schedule.run(self.app.world_mut());
+    }
+}
+
+// Mostly low levels as in a running world, a few ready to break through and a few dead.
+fn populate(world: &mut World, population: usize) {
+    let mut rng = WyRand::seed_from_u64(SEED);
+    let cultivators: Vec<_> = (0..population)
+        .map(|_| {
+            let idx = (rng.random::<f64>().powi(3) * 7.0) as usize;
+            let level = std::iter::successors(Some(Level::Foundation), Level::next_level)
+                .nth(idx)
+                .unwrap();
+            let required = level.required_cultivation();
+            let next = level
+                .next_level()
+                .map_or(required * 2, |l| l.required_cultivation());
+            let lifespan = level.total_lifespan();
+            (
+                Cultivation {
+                    level,
+                    cultivation: rng.random_range(required..next + next / 10),
+                },
+                Courage {
+                    courage: rng.random(),
+                },
+                Life {
+                    age: rng.random_range(20..lifespan),
+                    lifespan,
+                    alive: rng.random::<f64>() > 0.05,
+                    ticks: 0,
+                },
+            )
+        })
+        .collect();
+    world.spawn_batch(cultivators);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixture() {
+        let run = |stage| {
+            let mut fixture = Fixture::new(stage, 1000);
+            let dead = |fixture: &mut Fixture| {
+                let world = fixture.app.world_mut();
+                let mut query = world.query::<&Life>();
+                query.iter(world).filter(|l| !l.alive).count()
+            };
+            let before = dead(&mut fixture);
+            fixture.run();
+            (before, dead(&mut fixture))
+        };
+        let (before, after) = run(Stage::Battle);
+        assert!(after > before);
+        assert_eq!(run(Stage::Battle), (before, after));
+        let (before, after) = run(Stage::DespawnDead);
+        assert!(before > 0);
+        assert_eq!(after, 0);
+        for stage in Stage::ALL {
+            Fixture::new(stage, 100).run();
+        }
+    }
+}
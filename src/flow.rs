@@ -0,0 +1,216 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::time::common_conditions::on_timer;
+use serde::Serialize;
+
+use crate::battle::{self, BattleEvent, BattlePair, EscapeEvent};
+use crate::columnar;
+use crate::config::Config;
+use crate::cultivation::{BreakthroughEvent, Cultivation};
+use crate::demography;
+use crate::export::Exporter;
+use crate::level::Level;
+use crate::spawn::{DeathCause, DeathEvent, despawn_dead};
+use crate::system::{GamePlay, GlobalState, ecs_backend};
+
+// Events of one simulated year. Per-level counts are indexed by `Level::idx`.
+#[derive(Debug, Default, Clone, Serialize)]
+struct Flow {
+    year: u64,
+    spawns: usize,
+    encounters: usize,
+    fights: usize,
+    retreats: usize,
+    ascensions: usize,
+    kills: [usize; 7],
+    battle_deaths: [usize; 7],
+    old_age_deaths: [usize; 7],
+    breakthroughs: [usize; 7],
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct Flows {
+    current: Flow,
+    // The last full year, kept for the report.
+    last: Option<Flow>,
+}
+
+fn count_spawns(query: Query<(), Added<Cultivation>>, mut flows: ResMut<Flows>) {
+    flows.current.spawns += query.iter().count();
+}
+
+fn count_encounters(pairs: Res<BattlePair>, mut flows: ResMut<Flows>) {
+    flows.current.encounters += pairs.len();
+}
+
+fn count_fights(
+    mut ev_battle: EventReader<BattleEvent>,
+    query: Query<&Cultivation>,
+    mut flows: ResMut<Flows>,
+) {
+    // Ambushes report every kill as a battle event too, so their kills are counted here.
+    for ev in ev_battle.read() {
+        flows.current.fights += 1;
+        if !ev.killed {
+            continue;
+        }
+        if let Ok(killer) = query.get(ev.winner) {
+            flows.current.kills[killer.level.idx()] += 1;
+        }
+    }
+}
+
+fn count_flows(
+    mut ev_escape: EventReader<EscapeEvent>,
+    mut ev_breakthrough: EventReader<BreakthroughEvent>,
+    mut ev_death: EventReader<DeathEvent>,
+    mut flows: ResMut<Flows>,
+) {
+    let flow = &mut flows.current;
+    flow.retreats += ev_escape.read().count();
+    for ev in ev_breakthrough.read() {
+        debug!("{} 突破至{}", ev.entity, ev.level.name());
+        flow.breakthroughs[ev.level.idx()] += 1;
+    }
+    for ev in ev_death.read() {
+        match (ev.cause, ev.level) {
+            (DeathCause::Ascension, _) => flow.ascensions += 1,
+            (DeathCause::Battle, Some(level)) => flow.battle_deaths[level.idx()] += 1,
+            (DeathCause::OldAge, Some(level)) => flow.old_age_deaths[level.idx()] += 1,
+            _ => {}
+        }
+    }
+}
+
+// Closes the year on its last tick, appending it to the series and starting over.
+pub(crate) fn close_year(
+    mut flows: ResMut<Flows>,
+    state: Res<GlobalState>,
+    config: Res<Config>,
+    mut exporter: ResMut<Exporter>,
+) {
+    if state.tick + 1 < config.time.ticks_per_year {
+        return;
+    }
+    let mut flow = std::mem::take(&mut flows.current);
+    flow.year = state.year;
+    exporter.write(&config.export, "flow", [&flow]);
+    flows.last = Some(flow);
+}
+
+fn print_flows(flows: Res<Flows>) {
+    let Some(flow) = &flows.last else {
+        return;
+    };
+    info!(
+        "第{}年，新增修士{}名，相遇{}次，交手{}次，逃脱{}次，飞升{}名",
+        flow.year, flow.spawns, flow.encounters, flow.fights, flow.retreats, flow.ascensions
+    );
+    for level in std::iter::successors(Some(Level::Foundation), Level::next_level) {
+        let i = level.idx();
+        info!(
+            "第{}年，修为{}, 突破{}名，击杀{}次，战死{}名，寿尽{}名",
+            flow.year,
+            level.name(),
+            flow.breakthroughs[i],
+            flow.kills[i],
+            flow.battle_deaths[i],
+            flow.old_age_deaths[i]
+        );
+    }
+}
+
+// Flows are counted from the events of entities, which the columnar backend does not have.
+pub fn flow_plugin(app: &mut App) {
+    app.init_resource::<Flows>();
+    app.add_systems(
+        Update,
+        (
+            count_spawns
+                .after(Cultivation::try_advance)
+                .in_set(GamePlay::Spawn),
+            count_encounters
+                .before(battle::filter_battle)
+                .in_set(GamePlay::FilterPair),
+            count_fights.after(battle::battle).in_set(GamePlay::Battle),
+            (count_flows, close_year)
+                .chain()
+                .after(despawn_dead)
+                .after(columnar::step)
+                .after(demography::export_demography)
+                .in_set(GamePlay::Finish),
+            print_flows
+                .run_if(on_timer(Duration::from_secs(3)))
+                .in_set(GamePlay::Finish),
+        )
+            .run_if(ecs_backend),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::life::Life;
+
+    #[test]
+    fn test_count_flows() {
+        let mut app = App::new();
+        app.add_event::<EscapeEvent>();
+        app.add_event::<BreakthroughEvent>();
+        app.add_event::<DeathEvent>();
+        app.init_resource::<Flows>();
+        app.init_resource::<GlobalState>();
+        app.init_resource::<Exporter>();
+        let mut config = Config::default();
+        config.time.ticks_per_year = 2;
+        app.insert_resource(config);
+        app.add_systems(Update, (count_flows, close_year).chain());
+
+        let entity = app.world_mut().spawn_empty().id();
+        let death = |cause, level| DeathEvent {
+            life: Life {
+                age: 100,
+                lifespan: 100,
+                alive: false,
+                ticks: 0,
+            },
+            cause,
+            demonic: false,
+            level,
+            born: None,
+        };
+        app.world_mut()
+            .send_event(death(DeathCause::OldAge, Some(Level::Foundation)));
+        app.world_mut()
+            .send_event(death(DeathCause::Ascension, Some(Level::Mahayana)));
+        app.world_mut()
+            .send_event(death(DeathCause::Battle, Some(Level::GoldenCore)));
+        app.world_mut().send_event(BreakthroughEvent {
+            entity,
+            level: Level::GoldenCore,
+        });
+        app.world_mut().send_event(EscapeEvent {
+            fleer: entity,
+            chaser: entity,
+            talisman: false,
+        });
+        app.update();
+
+        let flow = &app.world().resource::<Flows>().current;
+        assert_eq!(flow.old_age_deaths[0], 1);
+        assert_eq!(flow.ascensions, 1);
+        assert_eq!(flow.battle_deaths[1], 1);
+        assert_eq!(flow.breakthroughs[1], 1);
+        assert_eq!(flow.retreats, 1);
+        assert!(app.world().resource::<Flows>().last.is_none());
+
+        // The last tick of the year moves the counters into `last`.
+        app.world_mut().resource_mut::<GlobalState>().tick = 1;
+        app.update();
+        let flows = app.world().resource::<Flows>();
+        assert_eq!(flows.current.retreats, 0);
+        assert_eq!(flows.last.as_ref().unwrap().retreats, 1);
+        assert_eq!(flows.last.as_ref().unwrap().year, 1);
+    }
+}
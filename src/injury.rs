@@ -0,0 +1,40 @@
+use bevy::prelude::*;
+
+use crate::system::GamePlay;
+
+// Injured cultivators neither fight nor cultivate until they recover.
+#[derive(Component, Debug)]
+pub struct Injury {
+    pub ticks: u64,
+}
+
+fn recover(mut commands: Commands, query: Query<(Entity, &mut Injury)>) {
+    for (entity, mut injury) in query {
+        injury.ticks = injury.ticks.saturating_sub(1);
+        if injury.ticks == 0 {
+            commands.entity(entity).remove::<Injury>();
+        }
+    }
+}
+
+pub fn injury_plugin(app: &mut App) {
+    app.add_systems(Update, recover.in_set(GamePlay::Finish));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recover() {
+        let mut app = App::new();
+        let entity = app.world_mut().spawn(Injury { ticks: 2 }).id();
+        app.add_systems(Update, recover);
+
+        app.update();
+        assert_eq!(app.world().get::<Injury>(entity).unwrap().ticks, 1);
+
+        app.update();
+        assert!(app.world().get::<Injury>(entity).is_none());
+    }
+}
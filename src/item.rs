@@ -0,0 +1,260 @@
+use bevy::prelude::*;
+use bevy_prng::WyRand;
+use bevy_rand::global::GlobalEntropy;
+use rand::Rng;
+use rand::seq::IndexedRandom;
+
+use crate::battle::{BattleEvent, EscapeEvent};
+use crate::config::Config;
+use crate::cultivation::Cultivation;
+use crate::life::Life;
+use crate::seclusion;
+use crate::sect;
+use crate::system::GamePlay;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Item {
+    LifespanPill,
+    CultivationPill,
+    EscapeTalisman,
+}
+
+impl Item {
+    const ALL: [Item; 3] = [
+        Item::LifespanPill,
+        Item::CultivationPill,
+        Item::EscapeTalisman,
+    ];
+}
+
+#[derive(Component, Debug, Default)]
+pub struct Inventory {
+    pub items: Vec<Item>,
+    // Years granted by the lifespan pills taken, kept on through breakthroughs.
+    pub extra_years: u64,
+}
+
+impl Inventory {
+    // Remove one `item` from the inventory, returning whether there was one.
+    pub fn take(&mut self, item: Item) -> bool {
+        match self.items.iter().position(|i| *i == item) {
+            Some(idx) => {
+                self.items.swap_remove(idx);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct ItemEvent {
+    pub entity: Entity,
+    pub item: Item,
+}
+
+fn equip(mut commands: Commands, config: Res<Config>, query: Query<Entity, Added<Cultivation>>) {
+    if !config.item.enabled {
+        return;
+    }
+    for entity in query {
+        commands.entity(entity).insert(Inventory::default());
+    }
+}
+
+pub(crate) fn find_items(
+    mut rng: GlobalEntropy<WyRand>,
+    config: Res<Config>,
+    query: Query<&mut Inventory>,
+) {
+    let find_rate = config.time.rate(config.item.find_rate);
+    for mut inventory in query {
+        if rng.random::<f64>() < find_rate {
+            inventory.items.push(*Item::ALL.choose(&mut rng).unwrap());
+        }
+    }
+}
+
+// The winner may pick up a dropped item, and strips the belongings of the slain.
+fn loot_items(
+    mut rng: GlobalEntropy<WyRand>,
+    config: Res<Config>,
+    mut ev_battle: EventReader<BattleEvent>,
+    mut query: Query<&mut Inventory>,
+) {
+    for ev in ev_battle.read() {
+        let Ok([mut winner, mut loser]) = query.get_many_mut([ev.winner, ev.loser]) else {
+            continue;
+        };
+        if ev.killed {
+            winner.items.append(&mut loser.items);
+        }
+        if rng.random::<f64>() < config.item.drop_rate {
+            winner.items.push(*Item::ALL.choose(&mut rng).unwrap());
+        }
+    }
+}
+
+fn burn_talismans(
+    mut ev_escape: EventReader<EscapeEvent>,
+    mut query: Query<&mut Inventory>,
+    mut ev_item: EventWriter<ItemEvent>,
+) {
+    for ev in ev_escape.read().filter(|ev| ev.talisman) {
+        if let Ok(mut inventory) = query.get_mut(ev.fleer)
+            && inventory.take(Item::EscapeTalisman)
+        {
+            ev_item.write(ItemEvent {
+                entity: ev.fleer,
+                item: Item::EscapeTalisman,
+            });
+        }
+    }
+}
+
+// Cultivation pills are taken at once, lifespan pills are saved for the last year of life.
+pub(crate) fn consume_items(
+    config: Res<Config>,
+    query: Query<(Entity, &mut Inventory, &mut Cultivation, &mut Life)>,
+    mut ev_item: EventWriter<ItemEvent>,
+) {
+    for (entity, mut inventory, mut cult, mut life) in query {
+        if !life.alive {
+            continue;
+        }
+        while inventory.take(Item::CultivationPill) {
+            let gain = cult.cultivation as f64 * config.item.cultivation_pill;
+            cult.cultivation += gain as u64;
+            ev_item.write(ItemEvent {
+                entity,
+                item: Item::CultivationPill,
+            });
+        }
+        if life.lifespan <= life.age + 1 && inventory.take(Item::LifespanPill) {
+            life.lifespan += config.item.lifespan_pill;
+            inventory.extra_years += config.item.lifespan_pill;
+            ev_item.write(ItemEvent {
+                entity,
+                item: Item::LifespanPill,
+            });
+        }
+    }
+}
+
+pub fn item_plugin(app: &mut App) {
+    app.add_event::<ItemEvent>();
+    app.add_systems(
+        Update,
+        (
+            (equip, find_items)
+                .chain()
+                .after(seclusion::seclude)
+                .in_set(GamePlay::Spawn),
+            (loot_items, burn_talismans)
+                .chain()
+                .after(sect::record_casualties)
+                .in_set(GamePlay::Battle),
+            consume_items
+                .before(Life::increase_age)
+                .in_set(GamePlay::Finish),
+        ),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cultivation::BreakthroughEvent;
+    use crate::level::Level;
+    use crate::system::GlobalState;
+    use bevy_rand::plugin::EntropyPlugin;
+
+    #[test]
+    fn test_consume_items() {
+        let mut app = App::new();
+        app.add_event::<ItemEvent>();
+        app.insert_resource(Config::default());
+        app.add_systems(Update, consume_items);
+
+        let items = vec![
+            Item::LifespanPill,
+            Item::CultivationPill,
+            Item::EscapeTalisman,
+        ];
+        let entity = app
+            .world_mut()
+            .spawn((
+                Inventory { items, ..default() },
+                Cultivation {
+                    level: Level::Foundation,
+                    cultivation: 100,
+                },
+                Life {
+                    age: 50,
+                    lifespan: 60,
+                    alive: true,
+                    ticks: 0,
+                },
+            ))
+            .id();
+        app.update();
+
+        // The lifespan pill is kept until death draws near.
+        let inventory = app.world().get::<Inventory>(entity).unwrap();
+        assert_eq!(inventory.items.len(), 2);
+        assert!(!inventory.items.contains(&Item::CultivationPill));
+        assert_eq!(
+            app.world().get::<Cultivation>(entity).unwrap().cultivation,
+            110
+        );
+
+        app.world_mut().get_mut::<Life>(entity).unwrap().age = 59;
+        app.update();
+        assert_eq!(app.world().get::<Life>(entity).unwrap().lifespan, 110);
+        let inventory = app.world().get::<Inventory>(entity).unwrap();
+        assert_eq!(inventory.items, vec![Item::EscapeTalisman]);
+    }
+
+    #[test]
+    fn test_pill_outlasts_breakthrough() {
+        let mut app = App::new();
+        app.add_plugins(EntropyPlugin::<WyRand>::default());
+        app.add_event::<ItemEvent>();
+        app.add_event::<BreakthroughEvent>();
+        app.init_resource::<GlobalState>();
+        app.insert_resource(Config::default());
+        app.add_systems(Update, (consume_items, Cultivation::try_advance).chain());
+
+        let entity = app
+            .world_mut()
+            .spawn((
+                Inventory {
+                    items: vec![Item::LifespanPill],
+                    ..default()
+                },
+                Cultivation {
+                    level: Level::Foundation,
+                    cultivation: 10,
+                },
+                Life {
+                    age: 99,
+                    lifespan: 100,
+                    alive: true,
+                    ticks: 0,
+                },
+            ))
+            .id();
+        app.update();
+        app.world_mut()
+            .get_mut::<Cultivation>(entity)
+            .unwrap()
+            .cultivation = Level::GoldenCore.required_cultivation();
+        app.update();
+
+        let pill = Config::default().item.lifespan_pill;
+        assert_eq!(
+            app.world().get::<Life>(entity).unwrap().lifespan,
+            Level::GoldenCore.total_lifespan() + pill
+        );
+    }
+}
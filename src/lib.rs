@@ -0,0 +1,95 @@
+mod accumulator;
+mod ambush;
+mod battle;
+mod benchmark;
+mod biography;
+mod columnar;
+mod config;
+mod cultivation;
+mod demography;
+mod demonic;
+mod export;
+// Seeded worlds for the benches, not part of the API.
+#[doc(hidden)]
+pub mod fixture;
+mod flow;
+mod injury;
+mod item;
+mod level;
+mod life;
+mod lineage;
+mod partition;
+mod perception;
+mod region;
+mod seclusion;
+mod sect;
+mod spawn;
+mod stat;
+mod system;
+mod vein;
+mod vengeance;
+
+use crate::cultivation::Cultivation;
+use crate::level::Level;
+use crate::life::Life;
+
+use ambush::ambush_plugin;
+use battle::battle_plugin;
+use benchmark::benchmark_system;
+use bevy::{log::LogPlugin, prelude::*};
+use bevy_prng::WyRand;
+use bevy_rand::plugin::EntropyPlugin;
+use biography::biography_plugin;
+use columnar::columnar_plugin;
+use config::config_plugin;
+use cultivation::cultivation_plugin;
+use demography::demography_plugin;
+use demonic::demonic_plugin;
+use export::export_plugin;
+use flow::flow_plugin;
+use injury::injury_plugin;
+use item::item_plugin;
+use life::life_plugin;
+use lineage::lineage_plugin;
+use perception::perception_plugin;
+use region::region_plugin;
+use seclusion::seclusion_plugin;
+use sect::sect_plugin;
+use spawn::spawn_plugin;
+use stat::stat_plugin;
+use system::game_system;
+use vein::vein_plugin;
+use vengeance::vengeance_plugin;
+
+// The whole simulation, ready to run.
+pub fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(LogPlugin::default())
+        .add_plugins(EntropyPlugin::<WyRand>::default())
+        .add_plugins(config_plugin)
+        .add_plugins(game_system)
+        .add_plugins(life_plugin)
+        .add_plugins(cultivation_plugin)
+        .add_plugins(battle_plugin)
+        .add_plugins(injury_plugin)
+        .add_plugins(sect_plugin)
+        .add_plugins(region_plugin)
+        .add_plugins(vein_plugin)
+        .add_plugins(lineage_plugin)
+        .add_plugins(vengeance_plugin)
+        .add_plugins(perception_plugin)
+        .add_plugins(ambush_plugin)
+        .add_plugins(item_plugin)
+        .add_plugins(seclusion_plugin)
+        .add_plugins(demonic_plugin)
+        .add_plugins(biography_plugin)
+        .add_plugins(spawn_plugin)
+        .add_plugins(columnar_plugin)
+        .add_plugins(benchmark_system)
+        .add_plugins(export_plugin)
+        .add_plugins(demography_plugin)
+        .add_plugins(flow_plugin)
+        .add_plugins(stat_plugin);
+    app
+}
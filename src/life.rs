@@ -1,5 +1,6 @@
 use bevy::prelude::*;
 
+use crate::config::Config;
 use crate::system::GamePlay;
 
 #[derive(Component, Debug, Clone)]
@@ -7,15 +8,25 @@ pub struct Life {
     pub age: u64,
     pub lifespan: u64,
     pub alive: bool,
+    // Ticks lived since the last birthday.
+    pub ticks: u64,
 }
 
 impl Life {
-    pub fn increase_age(query: Query<&mut Life>) {
+    pub(crate) fn tick(&mut self, ticks_per_year: u64) {
+        self.ticks += 1;
+        if self.ticks >= ticks_per_year {
+            self.ticks = 0;
+            self.age += 1;
+        }
+        if self.lifespan <= self.age {
+            self.alive = false;
+        }
+    }
+
+    pub fn increase_age(query: Query<&mut Life>, config: Res<Config>) {
         for mut life in query {
-            life.age += 1;
-            if life.lifespan <= life.age {
-                life.alive = false;
-            }
+            life.tick(config.time.ticks_per_year);
         }
     }
 }
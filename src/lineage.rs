@@ -0,0 +1,277 @@
+use std::collections::{BTreeMap, HashMap};
+
+use bevy::{ecs::query::QueryData, prelude::*};
+use bevy_prng::WyRand;
+use bevy_rand::global::GlobalEntropy;
+use itertools::Itertools;
+use rand::Rng;
+
+use crate::config::Config;
+use crate::cultivation::{Ascended, Cultivation};
+use crate::life::Life;
+use crate::spawn::despawn_dead;
+use crate::system::GamePlay;
+use crate::vein;
+
+// Place in the lineage tree, `founder` is the root master and is kept after they die.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Lineage {
+    pub founder: Entity,
+    pub master: Option<Entity>,
+    pub generation: u32,
+}
+
+#[derive(Component, Debug, Default)]
+pub struct Master {
+    pub disciples: Vec<Entity>,
+}
+
+#[derive(QueryData)]
+#[query_data(mutable)]
+pub(crate) struct MentorQuery {
+    entity: Entity,
+    cultivation: &'static mut Cultivation,
+    life: &'static Life,
+    master: Option<&'static mut Master>,
+    lineage: Option<&'static Lineage>,
+}
+
+pub(crate) fn take_disciples(
+    mut commands: Commands,
+    mut rng: GlobalEntropy<WyRand>,
+    config: Res<Config>,
+    mut query: Query<MentorQuery>,
+) {
+    let lineage = &config.lineage;
+    if !lineage.enabled {
+        return;
+    }
+    let mut newcomers = Vec::new();
+    let mut masters = Vec::new();
+    for i in query.iter_mut() {
+        let taken = i.master.map_or(0, |m| m.disciples.len());
+        if i.cultivation.is_added() {
+            newcomers.push(i.entity);
+        } else if i.life.alive
+            && i.cultivation.level.idx() >= lineage.master_level
+            && taken < lineage.max_disciples
+        {
+            masters.push((i.entity, lineage.max_disciples - taken));
+        }
+    }
+
+    let mut assigned: BTreeMap<Entity, Vec<Entity>> = BTreeMap::new();
+    for disciple in newcomers {
+        if masters.is_empty() || rng.random::<f64>() >= lineage.disciple_rate {
+            continue;
+        }
+        let idx = rng.random_range(0..masters.len());
+        let (master, vacancy) = &mut masters[idx];
+        assigned.entry(*master).or_default().push(disciple);
+        *vacancy -= 1;
+        if *vacancy == 0 {
+            masters.swap_remove(idx);
+        }
+    }
+
+    for (master, disciples) in assigned {
+        let i = query.get_mut(master).unwrap();
+        let master_lineage = i.lineage.copied().unwrap_or_else(|| {
+            let founder = Lineage {
+                founder: master,
+                master: None,
+                generation: 0,
+            };
+            commands.entity(master).insert(founder);
+            founder
+        });
+        match i.master {
+            Some(mut taken) => taken.disciples.extend(&disciples),
+            None => {
+                commands.entity(master).insert(Master {
+                    disciples: disciples.clone(),
+                });
+            }
+        }
+        for disciple in disciples {
+            query.get_mut(disciple).unwrap().cultivation.cultivation += lineage.boost;
+            commands.entity(disciple).insert(Lineage {
+                founder: master_lineage.founder,
+                master: Some(master),
+                generation: master_lineage.generation + 1,
+            });
+        }
+    }
+}
+
+fn prune_disciples(masters: Query<&mut Master>, lives: Query<&Life>) {
+    for mut master in masters {
+        master
+            .disciples
+            .retain(|d| lives.get(*d).is_ok_and(|l| l.alive));
+    }
+}
+
+fn inherit(
+    config: Res<Config>,
+    mut query: Query<(Entity, &Life, &mut Cultivation, Option<&Lineage>)>,
+    masters: Query<(), (With<Master>, Without<Ascended>)>,
+) {
+    let legacies: HashMap<Entity, f64> = query
+        .iter()
+        .filter(|(e, life, ..)| !life.alive && masters.contains(*e))
+        .map(|(e, _, cult, _)| (e, cult.cultivation as f64 * config.lineage.inheritance))
+        .collect();
+    if legacies.is_empty() {
+        return;
+    }
+    let heirs = query
+        .iter()
+        .filter(|(_, life, ..)| life.alive)
+        .filter_map(|(e, _, _, lineage)| Some((lineage?.master?, e)))
+        .filter(|(master, _)| legacies.contains_key(master))
+        .into_group_map();
+    for (master, heirs) in heirs {
+        let share = (legacies[&master] / heirs.len() as f64) as u64;
+        for heir in heirs {
+            query.get_mut(heir).unwrap().2.cultivation += share;
+        }
+    }
+}
+
+pub fn lineage_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            (prune_disciples, take_disciples)
+                .chain()
+                .after(vein::claim_veins)
+                .in_set(GamePlay::Spawn),
+            inherit
+                .after(Life::increase_age)
+                .after(Cultivation::try_ascend)
+                .before(despawn_dead)
+                .in_set(GamePlay::Finish),
+        ),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::Level;
+    use bevy_rand::plugin::EntropyPlugin;
+
+    fn life() -> Life {
+        Life {
+            age: 100,
+            lifespan: 1000,
+            alive: true,
+            ticks: 0,
+        }
+    }
+
+    #[test]
+    fn test_take_disciples() {
+        let mut app = App::new();
+        app.add_plugins(EntropyPlugin::<WyRand>::default());
+        let mut config = Config::default();
+        config.lineage.enabled = true;
+        config.lineage.disciple_rate = 1.0;
+        config.lineage.max_disciples = 1;
+        app.insert_resource(config);
+        app.add_systems(Update, take_disciples);
+
+        let master = app
+            .world_mut()
+            .spawn((
+                Cultivation {
+                    level: Level::GoldenCore,
+                    cultivation: 100,
+                },
+                life(),
+            ))
+            .id();
+        app.update();
+        let disciples: Vec<Entity> = (0..2)
+            .map(|_| {
+                app.world_mut()
+                    .spawn((
+                        Cultivation {
+                            level: Level::Foundation,
+                            cultivation: 10,
+                        },
+                        life(),
+                    ))
+                    .id()
+            })
+            .collect();
+        app.update();
+
+        assert_eq!(
+            app.world().get::<Master>(master).unwrap().disciples.len(),
+            1
+        );
+        assert_eq!(app.world().get::<Lineage>(master).unwrap().generation, 0);
+        let taught: Vec<_> = disciples
+            .iter()
+            .filter_map(|d| app.world().get::<Lineage>(*d))
+            .collect();
+        assert_eq!(taught.len(), 1);
+        assert_eq!(taught[0].founder, master);
+        assert_eq!(taught[0].generation, 1);
+    }
+
+    #[test]
+    fn test_inherit() {
+        let mut app = App::new();
+        let mut config = Config::default();
+        config.lineage.inheritance = 0.5;
+        app.insert_resource(config);
+        app.add_systems(Update, inherit);
+
+        let mut dead = life();
+        dead.alive = false;
+        let master = app.world_mut().spawn_empty().id();
+        let lineage = Lineage {
+            founder: master,
+            master: Some(master),
+            generation: 1,
+        };
+        let disciples: Vec<Entity> = (0..2)
+            .map(|_| {
+                app.world_mut()
+                    .spawn((
+                        Cultivation {
+                            level: Level::Foundation,
+                            cultivation: 10,
+                        },
+                        life(),
+                        lineage,
+                    ))
+                    .id()
+            })
+            .collect();
+        app.world_mut().entity_mut(master).insert((
+            Cultivation {
+                level: Level::GoldenCore,
+                cultivation: 200,
+            },
+            dead,
+            Master {
+                disciples: disciples.clone(),
+            },
+        ));
+        app.update();
+
+        for disciple in disciples {
+            assert_eq!(
+                app.world()
+                    .get::<Cultivation>(disciple)
+                    .unwrap()
+                    .cultivation,
+                60
+            );
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use std::collections::BTreeMap;
+
+use bevy::tasks::{ComputeTaskPool, TaskPool};
+use bevy_prng::WyRand;
+use rand::SeedableRng;
+
+// Random stream of the `index`th partition of a tick, all drawn from the one `seed`.
+fn stream(seed: u64, index: u64) -> WyRand {
+    WyRand::seed_from_u64(seed ^ index.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+}
+
+// Runs `f` over the partitions on the compute pool. Each partition gets its own stream and the
+// results come back in key order, so the outcome only depends on the seed, not on scheduling.
+pub(crate) fn par_map<K, T, R>(
+    seed: u64,
+    partitions: BTreeMap<K, T>,
+    f: impl Fn(&mut WyRand, K, T) -> R + Sync,
+) -> Vec<R>
+where
+    K: Send,
+    T: Send,
+    R: Send + 'static,
+{
+    let f = &f;
+    ComputeTaskPool::get_or_init(TaskPool::default).scope(|scope| {
+        for (index, (key, partition)) in partitions.into_iter().enumerate() {
+            scope.spawn(async move { f(&mut stream(seed, index as u64), key, partition) });
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_par_map() {
+        let partitions: BTreeMap<_, _> = (0..16).map(|k| (k, k * 10)).collect();
+        let draw = |seed| {
+            par_map(seed, partitions.clone(), |rng, k, v| {
+                (k + v, rng.random::<u64>())
+            })
+        };
+        let first = draw(42);
+        assert_eq!(
+            first.iter().map(|r| r.0).collect::<Vec<_>>(),
+            (0..16).map(|k| k * 11).collect::<Vec<_>>()
+        );
+        assert_eq!(first, draw(42));
+        assert_ne!(first, draw(43));
+    }
+}
@@ -0,0 +1,109 @@
+use bevy::prelude::*;
+use bevy_prng::WyRand;
+use bevy_rand::global::GlobalEntropy;
+use rand::Rng;
+
+use crate::battle::BattleQueryReadOnlyItem;
+use crate::config::{Config, PerceptionConfig};
+use crate::cultivation::Cultivation;
+use crate::lineage;
+use crate::system::GamePlay;
+
+// Fraction of a cultivator's cultivation hidden from the eyes of others.
+#[derive(Component, Debug)]
+pub struct Concealment {
+    pub skill: f64,
+}
+
+// Cultivation of the observed cultivator as it appears to an opponent.
+pub(crate) fn perceive(
+    rng: &mut impl Rng,
+    config: &PerceptionConfig,
+    observed: &BattleQueryReadOnlyItem,
+) -> Cultivation {
+    let skill = observed.concealment.map_or(0.0, |c| c.skill);
+    let error = if config.noise > 0.0 {
+        rng.random_range(-config.noise..config.noise)
+    } else {
+        0.0
+    };
+    let shown = observed.cultivation.cultivation as f64 * (1.0 - skill) * (1.0 + error);
+    Cultivation {
+        level: observed.cultivation.level,
+        cultivation: shown.max(1.0) as u64,
+    }
+}
+
+pub(crate) fn conceal(
+    mut commands: Commands,
+    mut rng: GlobalEntropy<WyRand>,
+    config: Res<Config>,
+    query: Query<Entity, Added<Cultivation>>,
+) {
+    let perception = &config.perception;
+    if !perception.enabled || perception.concealment <= 0.0 {
+        return;
+    }
+    for entity in query {
+        commands.entity(entity).insert(Concealment {
+            skill: rng.random_range(0.0..perception.concealment),
+        });
+    }
+}
+
+pub fn perception_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        conceal
+            .after(lineage::take_disciples)
+            .in_set(GamePlay::Spawn),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battle::{BattleQuery, Courage};
+    use crate::level::Level;
+    use crate::life::Life;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_perceive() {
+        let mut world = World::new();
+        let entity = world
+            .spawn((
+                Cultivation {
+                    level: Level::Foundation,
+                    cultivation: 100,
+                },
+                Courage { courage: 0.5 },
+                Life {
+                    age: 20,
+                    lifespan: 100,
+                    alive: true,
+                    ticks: 0,
+                },
+                Concealment { skill: 0.5 },
+            ))
+            .id();
+        let mut query = world.query::<BattleQuery>();
+        let observed = query.get(&world, entity).unwrap();
+        let mut rng = WyRand::seed_from_u64(42);
+
+        let exact = PerceptionConfig {
+            enabled: true,
+            noise: 0.0,
+            concealment: 0.5,
+        };
+        assert_eq!(perceive(&mut rng, &exact, &observed).cultivation, 50);
+
+        let noisy = PerceptionConfig {
+            enabled: true,
+            noise: 0.2,
+            concealment: 0.5,
+        };
+        let seen = perceive(&mut rng, &noisy, &observed).cultivation;
+        assert!((40..=60).contains(&seen));
+    }
+}
@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+use bevy_prng::WyRand;
+use bevy_rand::global::GlobalEntropy;
+use rand::Rng;
+
+use crate::config::Config;
+use crate::cultivation::Cultivation;
+use crate::sect;
+use crate::system::GamePlay;
+use crate::vein::VeinHolder;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Position {
+    pub x: u32,
+    pub y: u32,
+}
+
+impl Position {
+    // Neighbouring cell in one of four directions, wrapping around the edges.
+    pub(crate) fn step(&self, direction: u32, width: u32, height: u32) -> Position {
+        let (x, y) = (self.x, self.y);
+        match direction {
+            0 => Position {
+                x: (x + 1) % width,
+                y,
+            },
+            1 => Position {
+                x: (x + width - 1) % width,
+                y,
+            },
+            2 => Position {
+                x,
+                y: (y + 1) % height,
+            },
+            _ => Position {
+                x,
+                y: (y + height - 1) % height,
+            },
+        }
+    }
+}
+
+fn place(
+    mut commands: Commands,
+    mut rng: GlobalEntropy<WyRand>,
+    config: Res<Config>,
+    query: Query<Entity, Added<Cultivation>>,
+) {
+    let spatial = &config.spatial;
+    if !spatial.enabled {
+        return;
+    }
+    for entity in query {
+        commands.entity(entity).insert(Position {
+            x: rng.random_range(0..spatial.width),
+            y: rng.random_range(0..spatial.height),
+        });
+    }
+}
+
+// Vein holders stay in their cell to guard the vein.
+pub(crate) fn migrate(
+    mut rng: GlobalEntropy<WyRand>,
+    config: Res<Config>,
+    query: Query<&mut Position, Without<VeinHolder>>,
+) {
+    let spatial = &config.spatial;
+    for mut position in query {
+        if rng.random::<f64>() < config.time.rate(spatial.migration_rate) {
+            let direction = rng.random_range(0..4);
+            *position = position.step(direction, spatial.width, spatial.height);
+        }
+    }
+}
+
+pub fn region_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (place, migrate)
+            .chain()
+            .after(sect::recruit)
+            .in_set(GamePlay::Spawn),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_wraps() {
+        let corner = Position { x: 0, y: 0 };
+        assert_eq!(corner.step(0, 4, 3), Position { x: 1, y: 0 });
+        assert_eq!(corner.step(1, 4, 3), Position { x: 3, y: 0 });
+        assert_eq!(corner.step(2, 4, 3), Position { x: 0, y: 1 });
+        assert_eq!(corner.step(3, 4, 3), Position { x: 0, y: 2 });
+    }
+}
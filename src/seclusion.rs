@@ -0,0 +1,137 @@
+use bevy::prelude::*;
+use bevy_prng::WyRand;
+use bevy_rand::global::GlobalEntropy;
+use rand::Rng;
+
+use crate::battle::Courage;
+use crate::config::Config;
+use crate::demonic;
+use crate::injury::Injury;
+use crate::life::Life;
+use crate::system::GamePlay;
+use crate::vein::VeinHolder;
+
+// Secluded cultivators stay out of every encounter until they emerge.
+#[derive(Component, Debug)]
+pub struct Secluded {
+    pub ticks: u64,
+}
+
+type Recluse = (Without<Secluded>, Without<Injury>, Without<VeinHolder>);
+
+// Timid cultivators with time to spare may withdraw, vein holders stay to guard their vein.
+pub(crate) fn seclude(
+    mut commands: Commands,
+    mut rng: GlobalEntropy<WyRand>,
+    config: Res<Config>,
+    query: Query<(Entity, &Courage, &Life), Recluse>,
+) {
+    let seclusion = &config.seclusion;
+    if !seclusion.enabled {
+        return;
+    }
+    let rate = config.time.rate(seclusion.rate);
+    for (entity, courage, life) in query {
+        let headroom = life.lifespan.saturating_sub(life.age) as f64;
+        if courage.courage < seclusion.courage
+            && headroom >= seclusion.headroom * life.lifespan as f64
+            && rng.random::<f64>() < rate
+        {
+            commands.entity(entity).insert(Secluded {
+                ticks: seclusion.years * config.time.ticks_per_year,
+            });
+        }
+    }
+}
+
+fn emerge(mut commands: Commands, query: Query<(Entity, &mut Secluded)>) {
+    for (entity, mut secluded) in query {
+        secluded.ticks = secluded.ticks.saturating_sub(1);
+        if secluded.ticks == 0 {
+            commands.entity(entity).remove::<Secluded>();
+        }
+    }
+}
+
+pub fn seclusion_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            seclude.after(demonic::corrupt).in_set(GamePlay::Spawn),
+            emerge.in_set(GamePlay::Finish),
+        ),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_rand::plugin::EntropyPlugin;
+
+    #[test]
+    fn test_seclude() {
+        let mut app = App::new();
+        app.add_plugins(EntropyPlugin::<WyRand>::default());
+        let mut config = Config::default();
+        config.seclusion.enabled = true;
+        config.seclusion.rate = 1.0;
+        config.seclusion.years = 2;
+        app.insert_resource(config);
+        app.add_systems(Update, (seclude, emerge).chain());
+
+        let mut spawn = |courage, age| {
+            app.world_mut()
+                .spawn((
+                    Courage { courage },
+                    Life {
+                        age,
+                        lifespan: 100,
+                        alive: true,
+                        ticks: 0,
+                    },
+                ))
+                .id()
+        };
+        let timid = spawn(0.1, 20);
+        let brave = spawn(0.9, 20);
+        let old = spawn(0.1, 80);
+        app.update();
+
+        assert!(app.world().get::<Secluded>(timid).is_some());
+        assert!(app.world().get::<Secluded>(brave).is_none());
+        assert!(app.world().get::<Secluded>(old).is_none());
+    }
+
+    #[test]
+    fn test_emerge() {
+        let mut app = App::new();
+        app.add_plugins(EntropyPlugin::<WyRand>::default());
+        let mut config = Config::default();
+        config.seclusion.enabled = true;
+        config.seclusion.rate = 2.0;
+        config.seclusion.years = 2;
+        config.time.ticks_per_year = 2;
+        app.insert_resource(config);
+        app.add_systems(Update, (seclude, emerge).chain());
+
+        let timid = app
+            .world_mut()
+            .spawn((
+                Courage { courage: 0.1 },
+                Life {
+                    age: 20,
+                    lifespan: 100,
+                    alive: true,
+                    ticks: 0,
+                },
+            ))
+            .id();
+        // Secluded on the first tick and out at the end of the fourth.
+        for _ in 0..3 {
+            app.update();
+            assert!(app.world().get::<Secluded>(timid).is_some());
+        }
+        app.update();
+        assert!(app.world().get::<Secluded>(timid).is_none());
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_prng::WyRand;
+use bevy_rand::global::GlobalEntropy;
+use itertools::Itertools;
+use rand::Rng;
+use rand::seq::IndexedRandom;
+
+use crate::battle::{self, Available, BattleEvent, BattlePair};
+use crate::config::{Config, read_config_system};
+use crate::cultivation::Cultivation;
+use crate::demonic;
+use crate::level::Level;
+use crate::life::Life;
+use crate::spawn::spawn_cultivators;
+use crate::system::GamePlay;
+
+#[derive(Component, Debug)]
+pub struct Sect {
+    pub id: usize,
+    pub casualties: u64,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SectMember {
+    pub sect: Entity,
+}
+
+// Sect mates sworn to fight a killer in the next year, as (avenger, killer).
+#[derive(Resource, Default)]
+pub(crate) struct Revenge(Vec<(Entity, Entity)>);
+
+fn spawn_sects(mut commands: Commands, config: Res<Config>) {
+    commands.spawn_batch((0..config.sect.count).map(|id| Sect { id, casualties: 0 }));
+}
+
+pub(crate) fn recruit(
+    mut commands: Commands,
+    mut rng: GlobalEntropy<WyRand>,
+    config: Res<Config>,
+    sects: Query<Entity, With<Sect>>,
+    recruits: Query<Entity, Added<Cultivation>>,
+) {
+    let sects: Vec<Entity> = sects.iter().collect();
+    if sects.is_empty() {
+        return;
+    }
+    for entity in recruits {
+        if rng.random::<f64>() < config.sect.recruit_rate {
+            let sect = *sects.choose(&mut rng).unwrap();
+            commands.entity(entity).insert(SectMember { sect });
+        }
+    }
+}
+
+pub(crate) fn record_casualties(
+    mut ev_battle: EventReader<BattleEvent>,
+    mut rng: GlobalEntropy<WyRand>,
+    config: Res<Config>,
+    mut sects: Query<&mut Sect>,
+    members: Query<(Entity, &SectMember, &Cultivation, &Life), Available>,
+    cultivators: Query<(&Cultivation, Option<&SectMember>)>,
+    mut revenge: ResMut<Revenge>,
+) {
+    let mut mates: Option<HashMap<(Entity, Level), Vec<Entity>>> = None;
+    for ev in ev_battle.read() {
+        if !ev.killed {
+            continue;
+        }
+        let Ok((_, Some(member))) = cultivators.get(ev.loser) else {
+            continue;
+        };
+        if let Ok(mut sect) = sects.get_mut(member.sect) {
+            sect.casualties += 1;
+        }
+        if !config.sect.revenge {
+            continue;
+        }
+        let Ok((killer, killer_sect)) = cultivators.get(ev.winner) else {
+            continue;
+        };
+        if killer_sect == Some(member) {
+            continue;
+        }
+        let mates = mates.get_or_insert_with(|| {
+            members
+                .iter()
+                .filter(|(_, _, _, life)| life.alive)
+                .map(|(entity, m, c, _)| ((m.sect, c.level), entity))
+                .into_group_map()
+        });
+        if let Some(avenger) = mates
+            .get(&(member.sect, killer.level))
+            .and_then(|l| l.choose(&mut rng))
+        {
+            revenge.0.push((*avenger, ev.winner));
+        }
+    }
+}
+
+pub(crate) fn take_revenge(
+    mut revenge: ResMut<Revenge>,
+    mut pairs: ResMut<BattlePair>,
+    data: Query<&Life, Available>,
+) {
+    for (avenger, killer) in revenge.0.drain(..) {
+        let alive = |e| data.get(e).is_ok_and(|l| l.alive);
+        if avenger != killer && alive(avenger) && alive(killer) {
+            pairs.claim(avenger, killer);
+        }
+    }
+}
+
+pub fn sect_plugin(app: &mut App) {
+    app.init_resource::<Revenge>();
+    app.add_systems(Startup, spawn_sects.after(read_config_system));
+    app.add_systems(
+        Update,
+        (
+            recruit.after(spawn_cultivators).in_set(GamePlay::Spawn),
+            take_revenge
+                .after(battle::filter_battle)
+                .in_set(GamePlay::FilterPair),
+            record_casualties
+                .after(demonic::convert)
+                .in_set(GamePlay::Battle),
+        ),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_rand::plugin::EntropyPlugin;
+
+    #[test]
+    fn test_recruit() {
+        let mut app = App::new();
+        app.add_plugins(EntropyPlugin::<WyRand>::default());
+        let mut config = Config::default();
+        config.sect.count = 2;
+        config.sect.recruit_rate = 1.0;
+        app.insert_resource(config);
+        app.add_systems(Startup, spawn_sects);
+        app.add_systems(Update, recruit);
+        app.world_mut().spawn(Cultivation {
+            level: Level::Foundation,
+            cultivation: 10,
+        });
+        app.update();
+
+        let mut sects = app.world_mut().query::<&Sect>();
+        assert_eq!(sects.iter(app.world()).count(), 2);
+        let mut members = app.world_mut().query::<&SectMember>();
+        assert_eq!(members.iter(app.world()).count(), 1);
+    }
+
+    #[test]
+    fn test_record_casualties() {
+        let mut app = App::new();
+        app.add_plugins(EntropyPlugin::<WyRand>::default());
+        app.add_event::<BattleEvent>();
+        app.init_resource::<Revenge>();
+        let mut config = Config::default();
+        config.sect.revenge = true;
+        app.insert_resource(config);
+        app.add_systems(Update, record_casualties);
+
+        let sect = app
+            .world_mut()
+            .spawn(Sect {
+                id: 0,
+                casualties: 0,
+            })
+            .id();
+        let member = |level| {
+            (
+                Cultivation {
+                    level,
+                    cultivation: 100,
+                },
+                Life {
+                    age: 50,
+                    lifespan: 100,
+                    alive: true,
+                    ticks: 0,
+                },
+                SectMember { sect },
+            )
+        };
+        let victim = app.world_mut().spawn(member(Level::Foundation)).id();
+        let avenger = app.world_mut().spawn(member(Level::Foundation)).id();
+        app.world_mut().spawn(member(Level::GoldenCore));
+        let killer = app
+            .world_mut()
+            .spawn(Cultivation {
+                level: Level::Foundation,
+                cultivation: 100,
+            })
+            .id();
+        app.world_mut().get_mut::<Life>(victim).unwrap().alive = false;
+        app.world_mut().send_event(BattleEvent {
+            winner: killer,
+            loser: victim,
+            killed: true,
+        });
+        app.update();
+
+        assert_eq!(app.world().get::<Sect>(sect).unwrap().casualties, 1);
+        assert_eq!(app.world().resource::<Revenge>().0, vec![(avenger, killer)]);
+    }
+}
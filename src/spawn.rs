@@ -1,5 +1,4 @@
-use bevy::prelude::*;
-use bevy::state::commands;
+use bevy::{ecs::query::QueryData, prelude::*};
 use bevy_prng::WyRand;
 use bevy_rand::global::GlobalEntropy;
 use rand::Rng;
@@ -8,29 +7,75 @@ use crate::Cultivation;
 use crate::Level;
 use crate::Life;
 use crate::battle::Courage;
+use crate::biography::{Biography, Kills};
 use crate::config::Config;
-use crate::system::GamePlay;
+use crate::cultivation::Ascended;
+use crate::demonic::Demonic;
+use crate::system::{GamePlay, GlobalState, ecs_backend};
 
 #[derive(Bundle)]
 struct Cultivator {
     life: Life,
     cultivation: Cultivation,
     courage: Courage,
+    kills: Kills,
+    biography: Biography,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeathCause {
+    Battle,
+    OldAge,
+    Ascension,
+}
+
+impl DeathCause {
+    pub(crate) fn of(life: &Life, ascended: bool) -> Self {
+        if ascended {
+            DeathCause::Ascension
+        } else if life.lifespan <= life.age {
+            DeathCause::OldAge
+        } else {
+            DeathCause::Battle
+        }
+    }
 }
 
 #[derive(Event)]
 pub struct DeathEvent {
     pub life: Life,
+    pub cause: DeathCause,
+    pub demonic: bool,
+    pub level: Option<Level>,
+    // Year of birth, for cultivators that keep a biography.
+    pub born: Option<u64>,
+}
+
+#[derive(QueryData)]
+pub(crate) struct DeceasedQuery {
+    entity: Entity,
+    life: &'static Life,
+    cultivation: Option<&'static Cultivation>,
+    ascended: Has<Ascended>,
+    demonic: Has<Demonic>,
+    biography: Option<&'static Biography>,
 }
 
-fn spawn_cultivators(mut command: Commands, mut rng: GlobalEntropy<WyRand>, config: Res<Config>) {
-    let mut cultiavors: Vec<Cultivator> = Vec::with_capacity(config.spawn_per_year);
-    for _ in 0..config.spawn_per_year {
+pub fn spawn_cultivators(
+    mut command: Commands,
+    mut rng: GlobalEntropy<WyRand>,
+    config: Res<Config>,
+    state: Res<GlobalState>,
+) {
+    let count = config.time.share(config.spawn_per_year as u64, state.tick) as usize;
+    let mut cultiavors: Vec<Cultivator> = Vec::with_capacity(count);
+    for _ in 0..count {
         cultiavors.push(Cultivator {
             life: Life {
                 age: 20,
                 lifespan: 100,
                 alive: true,
+                ticks: 0,
             },
             cultivation: Cultivation {
                 level: Level::Foundation,
@@ -39,20 +84,32 @@ fn spawn_cultivators(mut command: Commands, mut rng: GlobalEntropy<WyRand>, conf
             courage: Courage {
                 courage: rng.random(),
             },
+            kills: Kills::default(),
+            biography: Biography {
+                born: state.year,
+                ..default()
+            },
         });
     }
     command.spawn_batch(cultiavors);
 }
 
-fn despawn_dead(
+pub(crate) fn despawn_dead(
     mut commands: Commands,
-    query: Query<(Entity, &Life)>,
+    query: Query<DeceasedQuery>,
     mut ev_death: EventWriter<DeathEvent>,
 ) {
-    for (entity, life) in query {
+    for i in query {
+        let life = i.life;
         if !life.alive {
-            commands.entity(entity).despawn();
-            ev_death.write(DeathEvent { life: life.clone() });
+            commands.entity(i.entity).despawn();
+            ev_death.write(DeathEvent {
+                life: life.clone(),
+                cause: DeathCause::of(life, i.ascended),
+                demonic: i.demonic,
+                level: i.cultivation.map(|c| c.level),
+                born: i.biography.map(|b| b.born),
+            });
         }
     }
 }
@@ -62,8 +119,12 @@ pub fn spawn_plugin(app: &mut App) {
     app.add_systems(
         Update,
         (
-            spawn_cultivators.in_set(GamePlay::Spawn),
-            despawn_dead.in_set(GamePlay::Finish),
+            spawn_cultivators
+                .run_if(ecs_backend)
+                .in_set(GamePlay::Spawn),
+            despawn_dead
+                .after(Life::increase_age)
+                .in_set(GamePlay::Finish),
         ),
     );
 }
@@ -78,6 +139,7 @@ mod tests {
         let mut app = App::new();
         app.add_plugins(EntropyPlugin::<WyRand>::default());
         app.init_resource::<Config>();
+        app.init_resource::<GlobalState>();
         app.add_systems(Update, spawn_cultivators);
         app.update();
 
@@ -90,8 +152,24 @@ mod tests {
     fn test_despawn_dead() {
         let mut app = App::new();
         app.add_event::<DeathEvent>();
-        let dead_entity = app.world_mut().spawn(Life { age: 100, lifespan: 100, alive: false }).id();
-        let alive_entity = app.world_mut().spawn(Life { age: 50, lifespan: 100, alive: true }).id();
+        let dead_entity = app
+            .world_mut()
+            .spawn(Life {
+                age: 100,
+                lifespan: 100,
+                alive: false,
+                ticks: 0,
+            })
+            .id();
+        let alive_entity = app
+            .world_mut()
+            .spawn(Life {
+                age: 50,
+                lifespan: 100,
+                alive: true,
+                ticks: 0,
+            })
+            .id();
 
         app.add_systems(Update, despawn_dead);
         app.update();
@@ -100,4 +178,3 @@ mod tests {
         assert!(app.world().get_entity(alive_entity).is_ok());
     }
 }
-
@@ -2,29 +2,66 @@ use bevy::prelude::*;
 use bevy::{ecs::query::QueryData, time::common_conditions::on_timer};
 use core::f64;
 use itertools::Itertools;
-use std::{collections::HashMap, time::Duration};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::time::Duration;
 
-use crate::battle::Courage;
+use crate::accumulator::{Sketch, Welford};
+use crate::ambush::AmbushEvent;
+use crate::battle::{BattleEvent, BattlePair, Courage, EscapeEvent, MisjudgeEvent};
+use crate::biography::{Biography, Kills};
+use crate::columnar::Columns;
+use crate::config::Config;
 use crate::cultivation::Cultivation;
+use crate::demonic::Demonic;
+use crate::export::Exporter;
+use crate::flow;
+use crate::item::{Item, ItemEvent};
 use crate::level::Level;
 use crate::life::Life;
-use crate::spawn::DeathEvent;
-use crate::system::GamePlay;
-
-#[derive(Resource, Default)]
-struct GlobalState {
-    year: u64,
-}
-
-fn increase_year(mut state: ResMut<GlobalState>) {
-    state.year += 1;
-}
+use crate::lineage::Lineage;
+use crate::region::Position;
+use crate::seclusion::Secluded;
+use crate::sect::{Sect, SectMember};
+use crate::spawn::{DeathCause, DeathEvent};
+use crate::system::{GamePlay, GlobalState, ecs_backend};
+use crate::vein::{SpiritualVein, VeinHolder};
+use crate::vengeance::VengeanceEvent;
 
 #[derive(QueryData)]
-struct CultivatorQuery {
+pub(crate) struct CultivatorQuery {
     life: &'static Life,
     cultivation: &'static Cultivation,
     courage: &'static Courage,
+    sect: Option<&'static SectMember>,
+    position: Option<&'static Position>,
+    vein: Has<VeinHolder>,
+    secluded: Has<Secluded>,
+    demonic: Has<Demonic>,
+    lineage: Option<&'static Lineage>,
+    kills: Option<&'static Kills>,
+    biography: Option<&'static Biography>,
+    entity: Entity,
+}
+
+// The figures of one cultivator the population statistics are made of.
+struct Sample {
+    courage: f64,
+    cultivation: u64,
+    age: u64,
+    lifespan: u64,
+}
+
+impl Sample {
+    fn new(i: &CultivatorQueryItem) -> Self {
+        Sample {
+            courage: i.courage.courage,
+            cultivation: i.cultivation.cultivation,
+            age: i.life.age,
+            lifespan: i.life.lifespan,
+        }
+    }
 }
 
 #[derive(Default, Debug)]
@@ -35,19 +72,139 @@ struct PerGroupStatistics {
 }
 
 impl PerGroupStatistics {
-    fn new<'a, T: IntoIterator<Item = &'a CultivatorQueryItem<'a>>>(
-        cultivators: T,
-    ) -> PerGroupStatistics {
-        let mut result = PerGroupStatistics::default();
-        for cult in cultivators {
-            result.size += 1;
-            result.courage += cult.courage.courage;
-            result.cultivation += cult.cultivation.cultivation as f64;
+    // Running means, updated in place so groups never have to be collected.
+    fn push(&mut self, sample: &Sample) {
+        self.size += 1;
+        let n = self.size as f64;
+        self.courage += (sample.courage - self.courage) / n;
+        self.cultivation += (sample.cultivation as f64 - self.cultivation) / n;
+    }
+}
+
+// Spread of a sample, with a histogram of equal bins over `range`.
+#[derive(Default, Debug, Serialize)]
+struct Distribution {
+    mean: f64,
+    std: f64,
+    min: f64,
+    max: f64,
+    p10: f64,
+    median: f64,
+    p90: f64,
+    range: (f64, f64),
+    histogram: Vec<usize>,
+}
+
+// Accumulates a `Distribution` one value at a time.
+#[derive(Default, Debug)]
+struct Spread {
+    moments: Welford,
+    sketch: Sketch,
+    range: (f64, f64),
+    histogram: Vec<usize>,
+}
+
+impl Spread {
+    fn reset(&mut self, range: (f64, f64), bins: usize) {
+        self.moments = Welford::default();
+        self.sketch.clear();
+        self.range = range;
+        self.histogram.clear();
+        self.histogram.resize(bins, 0);
+    }
+
+    fn push(&mut self, x: f64) {
+        self.moments.push(x);
+        self.sketch.push(x);
+        let bins = self.histogram.len();
+        if bins > 0 {
+            let width = (self.range.1 - self.range.0) / bins as f64;
+            // Values outside the range fall into the outermost bins.
+            let bin = ((x - self.range.0) / width).max(0.0) as usize;
+            self.histogram[bin.min(bins - 1)] += 1;
         }
-        result.courage /= result.size as f64;
-        result.cultivation /= result.size as f64;
-        result
     }
+
+    fn quantile(&self, q: f64) -> f64 {
+        let m = &self.moments;
+        self.sketch.quantile(q).clamp(m.min(), m.max())
+    }
+
+    fn distribution(&self) -> Distribution {
+        let m = &self.moments;
+        Distribution {
+            mean: m.mean(),
+            std: m.std(),
+            min: m.min(),
+            max: m.max(),
+            p10: self.quantile(0.1),
+            median: self.quantile(0.5),
+            p90: self.quantile(0.9),
+            range: self.range,
+            histogram: self.histogram.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct LevelDistribution {
+    year: u64,
+    level: &'static str,
+    courage: Distribution,
+    cultivation: Distribution,
+    age: Distribution,
+    remaining_lifespan: Distribution,
+    gini: f64,
+}
+
+#[derive(Default, Debug)]
+struct LevelSpread {
+    courage: Spread,
+    cultivation: Spread,
+    age: Spread,
+    remaining_lifespan: Spread,
+}
+
+impl LevelSpread {
+    fn reset(&mut self, level: Level, bins: usize) {
+        let required = level.required_cultivation() as f64;
+        let next = level
+            .next_level()
+            .map_or(required * 10.0, |l| l.required_cultivation() as f64);
+        let lifespan = (0.0, level.total_lifespan() as f64);
+        self.courage.reset((0.0, 1.0), bins);
+        self.cultivation.reset((required, next), bins);
+        self.age.reset(lifespan, bins);
+        self.remaining_lifespan.reset(lifespan, bins);
+    }
+
+    fn push(&mut self, sample: &Sample) {
+        self.courage.push(sample.courage);
+        self.cultivation.push(sample.cultivation as f64);
+        self.age.push(sample.age as f64);
+        let remaining = sample.lifespan.saturating_sub(sample.age);
+        self.remaining_lifespan.push(remaining as f64);
+    }
+
+    fn distribution(&self, year: u64, level: Level) -> LevelDistribution {
+        LevelDistribution {
+            year,
+            level: level.name(),
+            courage: self.courage.distribution(),
+            cultivation: self.cultivation.distribution(),
+            age: self.age.distribution(),
+            remaining_lifespan: self.remaining_lifespan.distribution(),
+            gini: self.cultivation.sketch.gini(),
+        }
+    }
+}
+
+#[derive(Default, Debug)]
+struct SectStatistics {
+    id: usize,
+    size: usize,
+    level: f64,
+    casualties: u64,
 }
 
 #[derive(Debug, Default)]
@@ -58,38 +215,300 @@ struct Average {
 
 impl Average {
     fn digiest(&mut self, data: f64) {
-        self.average = (self.average * self.total as f64 + data) / (self.total + 1) as f64;
         self.total += 1;
+        self.average += (data - self.average) / self.total as f64;
+    }
+}
+
+// A cultivator's record as shown on the leaderboard.
+#[derive(Debug, Serialize)]
+struct Notable {
+    id: String,
+    level: &'static str,
+    cultivation: u64,
+    age: u64,
+    kills: usize,
+    born: Option<u64>,
+    breakthroughs: Vec<(&'static str, u64)>,
+    battle_gain: u64,
+    time_gain: u64,
+}
+
+impl Notable {
+    fn new(i: &CultivatorQueryItem) -> Self {
+        let biography = i.biography;
+        Notable {
+            id: i.entity.to_string(),
+            level: i.cultivation.level.name(),
+            cultivation: i.cultivation.cultivation,
+            age: i.life.age,
+            kills: i.kills.map_or(0, Kills::count),
+            born: biography.map(|b| b.born),
+            breakthroughs: biography
+                .map(|b| {
+                    b.breakthroughs
+                        .iter()
+                        .map(|(l, y)| (l.name(), *y))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            battle_gain: biography.map_or(0, |b| b.battle_gain),
+            time_gain: biography.map_or(0, |b| b.time_gain),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Leaderboard {
+    year: u64,
+    cultivation: Vec<Notable>,
+    kills: Vec<Notable>,
+    age: Vec<Notable>,
+}
+
+// The `n` largest keys seen, kept in a min-heap so the smallest is evicted first.
+#[derive(Debug, Default)]
+struct TopN {
+    heap: BinaryHeap<Reverse<(u64, Entity)>>,
+}
+
+impl TopN {
+    fn push(&mut self, key: u64, entity: Entity, n: usize) {
+        self.heap.push(Reverse((key, entity)));
+        if self.heap.len() > n {
+            self.heap.pop();
+        }
+    }
+
+    // Entities from the largest key down, leaving the heap empty.
+    fn drain(&mut self) -> impl Iterator<Item = Entity> {
+        let mut top: Vec<_> = self.heap.drain().map(|Reverse(k)| k).collect();
+        top.sort_unstable_by(|a, b| b.cmp(a));
+        top.into_iter().map(|(_, e)| e)
     }
 }
 
 #[derive(Resource, Default, Debug)]
-struct XiuxianStatistics {
+pub(crate) struct XiuxianStatistics {
     per_level_stat: HashMap<Level, PerGroupStatistics>,
     global_stat: PerGroupStatistics,
+    per_sect_stat: Vec<SectStatistics>,
+    per_region_stat: HashMap<Position, PerGroupStatistics>,
+    veins: usize,
+    vein_holder_stat: PerGroupStatistics,
+    secluded_stat: PerGroupStatistics,
+    demonic_stat: PerGroupStatistics,
+    orthodox_stat: PerGroupStatistics,
+    demonic_death: Average,
+    leaderboard: Leaderboard,
+    top_cultivation: TopN,
+    top_kills: TopN,
+    top_age: TopN,
+    per_level_spread: HashMap<Level, LevelSpread>,
+    per_level_distribution: Vec<LevelDistribution>,
+    cultivation_sketch: Sketch,
+    cultivation_moments: Welford,
+    gini: f64,
+    founders: HashSet<Entity>,
+    sect_members: HashMap<Entity, (usize, usize)>,
+    per_generation_stat: HashMap<u32, PerGroupStatistics>,
+    lineages: usize,
+    lineage_share: f64,
     death: Average,
     death_by_battle: Average,
     death_by_age: Average,
+    ascension: Average,
+    escapes: usize,
+    injuries: usize,
+    unmatched: usize,
+    avenged: usize,
+    avengers_slain: usize,
+    misjudged: usize,
+    misjudged_deaths: usize,
+    ambushes: usize,
+    ambush_successes: usize,
+    items_used: HashMap<Item, usize>,
+}
+
+impl XiuxianStatistics {
+    // Starts a pass over the population. Accumulators are reset rather than rebuilt, keeping
+    // what they have allocated.
+    fn reset(&mut self, bins: usize) {
+        self.global_stat = PerGroupStatistics::default();
+        self.per_level_stat.clear();
+        self.per_region_stat.clear();
+        for (level, spread) in self.per_level_spread.iter_mut() {
+            spread.reset(*level, bins);
+        }
+    }
+
+    fn push(&mut self, level: Level, position: Option<Position>, sample: &Sample, bins: usize) {
+        self.global_stat.push(sample);
+        self.per_level_stat.entry(level).or_default().push(sample);
+        self.per_level_spread
+            .entry(level)
+            .or_insert_with(|| {
+                let mut spread = LevelSpread::default();
+                spread.reset(level, bins);
+                spread
+            })
+            .push(sample);
+        if let Some(position) = position {
+            self.per_region_stat
+                .entry(position)
+                .or_default()
+                .push(sample);
+        }
+    }
+
+    // Levels are merged into the population as a whole.
+    fn summarize(&mut self, year: u64) {
+        self.cultivation_sketch.clear();
+        self.cultivation_moments = Welford::default();
+        for spread in self.per_level_spread.values() {
+            self.cultivation_sketch.merge(&spread.cultivation.sketch);
+            self.cultivation_moments.merge(&spread.cultivation.moments);
+        }
+        self.gini = self.cultivation_sketch.gini();
+        self.per_level_distribution = self
+            .per_level_spread
+            .iter()
+            .filter(|(_, spread)| spread.courage.moments.count() > 0)
+            .sorted_by_key(|(level, _)| **level)
+            .map(|(level, spread)| spread.distribution(year, *level))
+            .collect();
+    }
 }
 
-fn update_stats(query: Query<CultivatorQuery>, mut stats: ResMut<XiuxianStatistics>) {
-    let cultivators: Vec<CultivatorQueryItem> = query.iter().collect();
-    stats.global_stat = PerGroupStatistics::new(&cultivators);
-    stats.per_level_stat = cultivators
-        .iter()
-        .into_group_map_by(|i| i.cultivation.level)
-        .into_iter()
-        .map(|(l, c)| (l, PerGroupStatistics::new(c)))
-        .collect();
+// One pass over the living per report. Cultivation changes every tick, so the accumulators are
+// filled afresh rather than kept up to date, they only spare the pass its allocations.
+pub(crate) fn update_stats(
+    query: Query<CultivatorQuery>,
+    sects: Query<(Entity, &Sect)>,
+    veins: Query<&SpiritualVein>,
+    config: Res<Config>,
+    state: Res<GlobalState>,
+    mut stats: ResMut<XiuxianStatistics>,
+) {
+    let stats = stats.as_mut();
+    let (n, bins) = (config.report.leaderboard, config.report.bins);
+    stats.reset(bins);
+    stats.vein_holder_stat = PerGroupStatistics::default();
+    stats.secluded_stat = PerGroupStatistics::default();
+    stats.demonic_stat = PerGroupStatistics::default();
+    stats.orthodox_stat = PerGroupStatistics::default();
+    stats.per_generation_stat.clear();
+    stats.founders.clear();
+    stats.sect_members.clear();
+    let (mut total, mut held) = (0, 0);
+    for i in &query {
+        let level = i.cultivation.level;
+        let cultivation = i.cultivation.cultivation;
+        let sample = Sample::new(&i);
+        stats.push(level, i.position.copied(), &sample, bins);
+        total += cultivation;
+        if i.vein {
+            stats.vein_holder_stat.push(&sample);
+        }
+        if i.secluded {
+            stats.secluded_stat.push(&sample);
+        }
+        if i.demonic {
+            stats.demonic_stat.push(&sample);
+        } else {
+            stats.orthodox_stat.push(&sample);
+        }
+        if let Some(lineage) = i.lineage {
+            stats.founders.insert(lineage.founder);
+            held += cultivation;
+            stats
+                .per_generation_stat
+                .entry(lineage.generation)
+                .or_default()
+                .push(&sample);
+        }
+        if let Some(member) = i.sect {
+            let (size, levels) = stats.sect_members.entry(member.sect).or_default();
+            *size += 1;
+            *levels += level.idx() + 1;
+        }
+        let kills = i.kills.map_or(0, Kills::count) as u64;
+        stats.top_cultivation.push(cultivation, i.entity, n);
+        stats.top_kills.push(kills, i.entity, n);
+        stats.top_age.push(i.life.age, i.entity, n);
+    }
+    stats.summarize(state.year);
+    stats.veins = veins.iter().count();
+    stats.lineages = stats.founders.len();
+    stats.lineage_share = if total == 0 {
+        0.0
+    } else {
+        held as f64 / total as f64
+    };
+    let notables = |top: &mut TopN| {
+        top.drain()
+            .filter_map(|e| query.get(e).ok())
+            .map(|i| Notable::new(&i))
+            .collect()
+    };
+    stats.leaderboard = Leaderboard {
+        year: state.year,
+        cultivation: notables(&mut stats.top_cultivation),
+        kills: notables(&mut stats.top_kills),
+        age: notables(&mut stats.top_age),
+    };
+    stats.per_sect_stat.clear();
+    for (entity, sect) in &sects {
+        let (size, levels) = stats.sect_members.get(&entity).copied().unwrap_or_default();
+        stats.per_sect_stat.push(SectStatistics {
+            id: sect.id,
+            size,
+            level: if size == 0 {
+                0.0
+            } else {
+                levels as f64 / size as f64
+            },
+            casualties: sect.casualties,
+        });
+    }
+    stats.per_sect_stat.sort_by_key(|s| s.id);
+}
+
+// The columnar backend keeps the population figures, entity-only ones stay empty.
+fn update_columnar_stats(
+    columns: Res<Columns>,
+    config: Res<Config>,
+    state: Res<GlobalState>,
+    mut stats: ResMut<XiuxianStatistics>,
+) {
+    let stats = stats.as_mut();
+    let bins = config.report.bins;
+    stats.reset(bins);
+    for row in 0..columns.len() {
+        let (cult, life) = (&columns.cultivation[row], &columns.life[row]);
+        let sample = Sample {
+            courage: columns.courage[row],
+            cultivation: cult.cultivation,
+            age: life.age,
+            lifespan: life.lifespan,
+        };
+        stats.push(cult.level, columns.position[row], &sample, bins);
+    }
+    stats.summarize(state.year);
+    stats.escapes = columns.escapes;
+    stats.injuries = columns.injuries;
+    stats.unmatched = columns.unmatched;
 }
 
 fn print_stats(stats: Res<XiuxianStatistics>, state: Res<GlobalState>) {
     info!(
-        "现在是第{}年，现有修士{}名，平均勇气值{:.3}，平均修为{:.3}",
+        "现在是第{}年，现有修士{}名，平均勇气值{:.3}，平均修为{:.3}，修为标准差{:.3}，修为基尼系数{:.3}",
         state.year,
         stats.global_stat.size,
         stats.global_stat.courage,
-        stats.global_stat.cultivation
+        stats.global_stat.cultivation,
+        stats.cultivation_moments.std(),
+        stats.gini
     );
 
     for (level, stat) in stats.per_level_stat.iter().sorted_by_key(|i| i.0) {
@@ -101,6 +520,80 @@ fn print_stats(stats: Res<XiuxianStatistics>, state: Res<GlobalState>) {
             stat.cultivation
         );
     }
+    for d in &stats.per_level_distribution {
+        let c = &d.cultivation;
+        info!(
+            "修为{}, 修为分布: 最低{}，P10 {:.1}，中位数{:.1}，P90 {:.1}，最高{}，标准差{:.3}，基尼系数{:.3}",
+            d.level, c.min, c.p10, c.median, c.p90, c.max, c.std, d.gini
+        );
+        info!(
+            "修为{}, 中位数: 勇气值{:.3}，年龄{:.1}，剩余寿元{:.1}",
+            d.level, d.courage.median, d.age.median, d.remaining_lifespan.median
+        );
+    }
+    for stat in &stats.per_sect_stat {
+        info!(
+            "宗门{}，现有门人{}名，平均境界Lv{:.3}，战死{}人",
+            stat.id, stat.size, stat.level, stat.casualties
+        );
+    }
+    if let Some((position, crowded)) = stats.per_region_stat.iter().max_by_key(|i| i.1.size) {
+        info!(
+            "有人区域{}个，最拥挤区域({}, {})有修士{}名，平均修为{:.3}",
+            stats.per_region_stat.len(),
+            position.x,
+            position.y,
+            crowded.size,
+            crowded.cultivation
+        );
+    }
+    for (position, stat) in stats.per_region_stat.iter().sorted_by_key(|i| i.0) {
+        debug!(
+            "区域({}, {})，现有修士{}名，平均勇气值{:.3}，平均修为{:.3}",
+            position.x, position.y, stat.size, stat.courage, stat.cultivation
+        );
+    }
+    if stats.veins > 0 {
+        info!(
+            "灵脉{}处，占据者{}名，平均勇气值{:.3}，平均修为{:.3}",
+            stats.veins,
+            stats.vein_holder_stat.size,
+            stats.vein_holder_stat.courage,
+            stats.vein_holder_stat.cultivation
+        );
+    }
+    if stats.demonic_stat.size > 0 || stats.demonic_death.total > 0 {
+        info!(
+            "魔修{}名，平均勇气值{:.3}，平均修为{:.3}，死亡{}名，平均寿命{:.3}",
+            stats.demonic_stat.size,
+            stats.demonic_stat.courage,
+            stats.demonic_stat.cultivation,
+            stats.demonic_death.total,
+            stats.demonic_death.average
+        );
+        info!(
+            "正道修士{}名，平均勇气值{:.3}，平均修为{:.3}",
+            stats.orthodox_stat.size, stats.orthodox_stat.courage, stats.orthodox_stat.cultivation
+        );
+    }
+    if stats.secluded_stat.size > 0 {
+        info!(
+            "闭关修士{}名，平均勇气值{:.3}，平均修为{:.3}",
+            stats.secluded_stat.size, stats.secluded_stat.courage, stats.secluded_stat.cultivation
+        );
+    }
+    if stats.lineages > 0 {
+        info!(
+            "师门传承{}支，门下修为占比{:.3}",
+            stats.lineages, stats.lineage_share
+        );
+    }
+    for (generation, stat) in stats.per_generation_stat.iter().sorted_by_key(|i| i.0) {
+        info!(
+            "第{}代传人{}名，平均勇气值{:.3}，平均修为{:.3}",
+            generation, stat.size, stat.courage, stat.cultivation
+        );
+    }
     info!(
         "死亡人数: {}，平均寿命: {}",
         stats.death.total, stats.death.average
@@ -113,33 +606,191 @@ fn print_stats(stats: Res<XiuxianStatistics>, state: Res<GlobalState>) {
         "年老死亡: {}，平均寿命: {}",
         stats.death_by_age.total, stats.death_by_age.average
     );
+    info!(
+        "飞升人数: {}，平均年龄: {}",
+        stats.ascension.total, stats.ascension.average
+    );
+    info!(
+        "逃跑成功: {}，战败负伤: {}，轮空: {}",
+        stats.escapes, stats.injuries, stats.unmatched
+    );
+    if !stats.items_used.is_empty() {
+        let used = |item| stats.items_used.get(&item).copied().unwrap_or(0);
+        info!(
+            "服用延寿丹: {}，服用聚气丹: {}，使用遁符: {}",
+            used(Item::LifespanPill),
+            used(Item::CultivationPill),
+            used(Item::EscapeTalisman)
+        );
+    }
+    if stats.ambushes > 0 {
+        info!("伏击: {}，得手: {}", stats.ambushes, stats.ambush_successes);
+    }
+    if stats.misjudged > 0 {
+        info!(
+            "误判出手: {}，因误判身死: {}",
+            stats.misjudged, stats.misjudged_deaths
+        );
+    }
+    if stats.avenged + stats.avengers_slain > 0 {
+        info!(
+            "大仇得报: {}，复仇身死: {}",
+            stats.avenged, stats.avengers_slain
+        );
+    }
+    let rankings = [
+        ("修为榜", &stats.leaderboard.cultivation),
+        ("杀戮榜", &stats.leaderboard.kills),
+        ("寿元榜", &stats.leaderboard.age),
+    ];
+    for (title, notables) in rankings {
+        for (rank, n) in notables.iter().enumerate() {
+            let breakthroughs = n
+                .breakthroughs
+                .iter()
+                .map(|(level, year)| format!("{}年{}", year, level))
+                .join("，");
+            info!(
+                "{}第{}名: {}，境界{}，修为{}（斗法所得{}，苦修所得{}），年龄{}，击杀{}人，突破: {}",
+                title,
+                rank + 1,
+                n.id,
+                n.level,
+                n.cultivation,
+                n.battle_gain,
+                n.time_gain,
+                n.age,
+                n.kills,
+                breakthroughs
+            );
+        }
+    }
+}
+
+fn export_stats(
+    stats: Res<XiuxianStatistics>,
+    config: Res<Config>,
+    mut exporter: ResMut<Exporter>,
+) {
+    exporter.write(&config.export, "leaderboard", [&stats.leaderboard]);
+    exporter.write(
+        &config.export,
+        "distribution",
+        &stats.per_level_distribution,
+    );
 }
 
 fn collect_death(mut ev_death: EventReader<DeathEvent>, mut stats: ResMut<XiuxianStatistics>) {
     for ev in ev_death.read() {
         let age = ev.life.age as f64;
+        match ev.cause {
+            DeathCause::Ascension => {
+                stats.ascension.digiest(age);
+                continue;
+            }
+            DeathCause::OldAge => stats.death_by_age.digiest(age),
+            DeathCause::Battle => stats.death_by_battle.digiest(age),
+        }
         stats.death.digiest(age);
-        if ev.life.lifespan == ev.life.age {
-            stats.death_by_age.digiest(age);
+        if ev.demonic {
+            stats.demonic_death.digiest(age);
+        }
+    }
+}
+
+fn collect_escape(mut ev_escape: EventReader<EscapeEvent>, mut stats: ResMut<XiuxianStatistics>) {
+    for ev in ev_escape.read() {
+        debug!("{} 从 {} 手中逃脱", ev.fleer, ev.chaser);
+        stats.escapes += 1;
+    }
+}
+
+fn collect_battle(mut ev_battle: EventReader<BattleEvent>, mut stats: ResMut<XiuxianStatistics>) {
+    for ev in ev_battle.read() {
+        if !ev.killed {
+            debug!("{} 败于 {}，负伤逃生", ev.loser, ev.winner);
+            stats.injuries += 1;
+        }
+    }
+}
+
+fn collect_unmatched(pairs: Res<BattlePair>, mut stats: ResMut<XiuxianStatistics>) {
+    stats.unmatched += pairs.unmatched;
+}
+
+fn collect_vengeance(
+    mut ev_vengeance: EventReader<VengeanceEvent>,
+    mut stats: ResMut<XiuxianStatistics>,
+) {
+    for ev in ev_vengeance.read() {
+        if ev.avenged {
+            debug!("{} 手刃仇人 {}", ev.avenger, ev.killer);
+            stats.avenged += 1;
         } else {
-            stats.death_by_battle.digiest(age);
+            debug!("{} 复仇未果，死于 {} 之手", ev.avenger, ev.killer);
+            stats.avengers_slain += 1;
+        }
+    }
+}
+
+fn collect_misjudge(
+    mut ev_misjudge: EventReader<MisjudgeEvent>,
+    mut stats: ResMut<XiuxianStatistics>,
+) {
+    for ev in ev_misjudge.read() {
+        stats.misjudged += 1;
+        if ev.died {
+            debug!("{} 误判对手修为，身死道消", ev.entity);
+            stats.misjudged_deaths += 1;
         }
     }
 }
 
+fn collect_ambush(mut ev_ambush: EventReader<AmbushEvent>, mut stats: ResMut<XiuxianStatistics>) {
+    for ev in ev_ambush.read() {
+        stats.ambushes += 1;
+        if ev.success {
+            debug!("{}名修士合围{}得手", ev.attackers, ev.target);
+            stats.ambush_successes += 1;
+        }
+    }
+}
+
+fn collect_items(mut ev_item: EventReader<ItemEvent>, mut stats: ResMut<XiuxianStatistics>) {
+    for ev in ev_item.read() {
+        debug!("{} 使用了 {:?}", ev.entity, ev.item);
+        *stats.items_used.entry(ev.item).or_default() += 1;
+    }
+}
+
 pub fn stat_plugin(app: &mut App) {
-    app.init_resource::<XiuxianStatistics>()
-        .init_resource::<GlobalState>()
-        .add_systems(
-            Update,
+    app.init_resource::<XiuxianStatistics>().add_systems(
+        Update,
+        (
             (
-                (increase_year, collect_death).in_set(GamePlay::Spawn),
-                (update_stats, print_stats)
-                    .chain()
-                    .run_if(on_timer(Duration::from_secs(3)))
-                    .in_set(GamePlay::Finish),
-            ),
-        );
+                collect_death,
+                collect_escape,
+                collect_battle,
+                collect_vengeance,
+                collect_misjudge,
+                collect_ambush,
+                collect_items,
+            )
+                .chain()
+                .in_set(GamePlay::Spawn),
+            collect_unmatched.in_set(GamePlay::Battle),
+            (
+                update_stats.run_if(ecs_backend),
+                update_columnar_stats.run_if(not(ecs_backend)),
+                print_stats,
+                export_stats,
+            )
+                .chain()
+                .after(flow::close_year)
+                .run_if(on_timer(Duration::from_secs(3)))
+                .in_set(GamePlay::Finish),
+        ),
+    );
 }
 
 #[cfg(test)]
@@ -150,25 +801,60 @@ mod tests {
     fn test_per_group_statistics() {
         let mut world = World::new();
         world.spawn((
-            Life { age: 20, lifespan: 100, alive: true },
-            Cultivation { level: Level::Foundation, cultivation: 10 },
+            Life {
+                age: 20,
+                lifespan: 100,
+                alive: true,
+                ticks: 0,
+            },
+            Cultivation {
+                level: Level::Foundation,
+                cultivation: 10,
+            },
             Courage { courage: 0.5 },
         ));
         world.spawn((
-            Life { age: 20, lifespan: 100, alive: true },
-            Cultivation { level: Level::Foundation, cultivation: 20 },
+            Life {
+                age: 20,
+                lifespan: 100,
+                alive: true,
+                ticks: 0,
+            },
+            Cultivation {
+                level: Level::Foundation,
+                cultivation: 20,
+            },
             Courage { courage: 0.7 },
         ));
 
         let mut query = world.query::<CultivatorQuery>();
         let items: Vec<_> = query.iter(&world).collect();
-        let stats = PerGroupStatistics::new(&items);
+        let mut stats = PerGroupStatistics::default();
+        for item in &items {
+            stats.push(&Sample::new(item));
+        }
 
         assert_eq!(stats.size, 2);
         assert_eq!(stats.courage, 0.6);
         assert_eq!(stats.cultivation, 15.0);
     }
 
+    #[test]
+    fn test_spread() {
+        let mut spread = Spread::default();
+        spread.reset((0.0, 10.0), 5);
+        for x in 1..=11 {
+            spread.push(x as f64);
+        }
+        let d = spread.distribution();
+        assert_eq!((d.min, d.max, d.mean), (1.0, 11.0, 6.0));
+        assert!((d.std - 10.0f64.sqrt()).abs() < 1e-9);
+        for (estimate, exact) in [(d.p10, 2.0), (d.median, 6.0), (d.p90, 10.0)] {
+            assert!((estimate - exact).abs() / exact < 0.02);
+        }
+        assert_eq!(d.histogram, vec![1, 2, 2, 2, 4]);
+    }
+
     #[test]
     fn test_average() {
         let mut avg = Average::default();
@@ -188,10 +874,40 @@ mod tests {
 
         let mut death_events = app.world_mut().resource_mut::<Events<DeathEvent>>();
         death_events.send(DeathEvent {
-            life: Life { age: 100, lifespan: 100, alive: false },
+            life: Life {
+                age: 100,
+                lifespan: 100,
+                alive: false,
+                ticks: 0,
+            },
+            cause: DeathCause::OldAge,
+            demonic: false,
+            level: Some(Level::Foundation),
+            born: None,
+        });
+        death_events.send(DeathEvent {
+            life: Life {
+                age: 50,
+                lifespan: 120,
+                alive: false,
+                ticks: 0,
+            },
+            cause: DeathCause::Battle,
+            demonic: false,
+            level: Some(Level::Foundation),
+            born: None,
         });
         death_events.send(DeathEvent {
-            life: Life { age: 50, lifespan: 120, alive: false },
+            life: Life {
+                age: 5000,
+                lifespan: 88888900,
+                alive: false,
+                ticks: 0,
+            },
+            cause: DeathCause::Ascension,
+            demonic: false,
+            level: Some(Level::Mahayana),
+            born: None,
         });
 
         app.update();
@@ -203,5 +919,7 @@ mod tests {
         assert_eq!(stats.death_by_age.average, 100.0);
         assert_eq!(stats.death_by_battle.total, 1);
         assert_eq!(stats.death_by_battle.average, 50.0);
+        assert_eq!(stats.ascension.total, 1);
+        assert_eq!(stats.ascension.average, 5000.0);
     }
 }
@@ -1,4 +1,8 @@
 use bevy::prelude::*;
+use bevy_prng::WyRand;
+use bevy_rand::global::GlobalRngEntity;
+
+use crate::config::{Backend, Config, read_config_system};
 
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum GamePlay {
@@ -9,7 +13,41 @@ pub enum GamePlay {
     Finish,
 }
 
+// The tick currently being played, `tick` counts from 0 within `year`.
+#[derive(Resource, Debug)]
+pub struct GlobalState {
+    pub year: u64,
+    pub tick: u64,
+}
+
+impl Default for GlobalState {
+    fn default() -> Self {
+        GlobalState { year: 1, tick: 0 }
+    }
+}
+
+pub(crate) fn advance_time(mut state: ResMut<GlobalState>, config: Res<Config>) {
+    state.tick += 1;
+    if state.tick >= config.time.ticks_per_year {
+        state.tick = 0;
+        state.year += 1;
+    }
+}
+
+pub(crate) fn ecs_backend(config: Res<Config>) -> bool {
+    config.backend == Backend::Ecs
+}
+
+pub(crate) fn seed_entropy(mut global: GlobalRngEntity<WyRand>, config: Res<Config>) {
+    if let Some(seed) = config.seed {
+        global.rng_commands().reseed(seed.to_le_bytes());
+    }
+}
+
 pub fn game_system(app: &mut App) {
+    app.init_resource::<GlobalState>();
+    app.add_systems(Startup, seed_entropy.after(read_config_system));
+    app.add_systems(Update, advance_time.after(GamePlay::Finish));
     app.configure_sets(
         Update,
         (
@@ -20,3 +58,27 @@ pub fn game_system(app: &mut App) {
         ),
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::schedule::{LogLevel, ScheduleBuildSettings};
+
+    // Systems left unordered may run either way round on the multi-threaded executor, which
+    // would break seeded runs wherever they share the global stream or the world.
+    #[test]
+    fn test_schedule_ordered() {
+        let mut app = crate::app();
+        app.edit_schedule(Update, |schedule| {
+            schedule.set_build_settings(ScheduleBuildSettings {
+                ambiguity_detection: LogLevel::Error,
+                ..default()
+            });
+        });
+        app.world_mut().schedule_scope(Update, |world, schedule| {
+            if let Err(e) = schedule.initialize(world) {
+                panic!("{}", e);
+            }
+        });
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use bevy_prng::WyRand;
+use bevy_rand::global::GlobalEntropy;
+use itertools::Itertools;
+use rand::Rng;
+use rand::seq::IndexedRandom;
+
+use crate::battle::{
+    self, Available, BattleEvent, BattlePair, BattleQueryReadOnly, EscapeEvent, will_battle,
+};
+use crate::config::Config;
+use crate::cultivation::Cultivation;
+use crate::injury::Injury;
+use crate::life::Life;
+use crate::region::{self, Position};
+use crate::sect;
+use crate::spawn::despawn_dead;
+use crate::system::{GamePlay, seed_entropy};
+
+// A spiritual vein, which lies in a cell of the grid when the spatial model is enabled.
+#[derive(Component, Debug)]
+pub struct SpiritualVein {
+    pub holder: Option<Entity>,
+}
+
+#[derive(Component, Debug)]
+pub struct VeinHolder {
+    pub vein: Entity,
+}
+
+type Claimant = (With<Cultivation>, Available, Without<VeinHolder>);
+
+fn spawn_veins(mut commands: Commands, mut rng: GlobalEntropy<WyRand>, config: Res<Config>) {
+    for _ in 0..config.vein.count {
+        let mut vein = commands.spawn(SpiritualVein { holder: None });
+        if config.spatial.enabled {
+            vein.insert(Position {
+                x: rng.random_range(0..config.spatial.width),
+                y: rng.random_range(0..config.spatial.height),
+            });
+        }
+    }
+}
+
+pub(crate) fn claim_veins(
+    mut commands: Commands,
+    mut rng: GlobalEntropy<WyRand>,
+    mut veins: Query<(Entity, &mut SpiritualVein, Option<&Position>)>,
+    candidates: Query<(Entity, Option<&Position>), Claimant>,
+) {
+    let mut locals: Option<HashMap<Option<Position>, Vec<Entity>>> = None;
+    for (entity, mut vein, position) in &mut veins {
+        if vein.holder.is_some() {
+            continue;
+        }
+        let locals = locals.get_or_insert_with(|| {
+            candidates
+                .iter()
+                .map(|(e, p)| (p.copied(), e))
+                .into_group_map()
+        });
+        let Some(local) = locals.get_mut(&position.copied()) else {
+            continue;
+        };
+        if local.is_empty() {
+            continue;
+        }
+        let holder = local.swap_remove(rng.random_range(0..local.len()));
+        vein.holder = Some(holder);
+        commands.entity(holder).insert(VeinHolder { vein: entity });
+    }
+}
+
+pub(crate) fn challenge_veins(
+    mut rng: GlobalEntropy<WyRand>,
+    config: Res<Config>,
+    veins: Query<(&SpiritualVein, Option<&Position>)>,
+    data: Query<BattleQueryReadOnly, (Available, Without<VeinHolder>)>,
+    holders: Query<BattleQueryReadOnly, With<VeinHolder>>,
+    mut pairs: ResMut<BattlePair>,
+    mut ev_escape: EventWriter<EscapeEvent>,
+) {
+    let mut locals = None;
+    for (vein, position) in veins {
+        let Some(holder) = vein.holder.and_then(|e| holders.get(e).ok()) else {
+            continue;
+        };
+        if rng.random::<f64>() >= config.time.rate(config.vein.challenge_rate) {
+            continue;
+        }
+        let locals = locals.get_or_insert_with(|| {
+            data.iter()
+                .map(|i| {
+                    let sect = i.sect.map(|s| s.sect);
+                    ((i.cultivation.level, i.position.copied()), (i.entity, sect))
+                })
+                .into_group_map()
+        });
+        // Sect mates never fight, so they do not contest each other's veins either.
+        let sect = holder.sect.map(|s| s.sect);
+        let key = (holder.cultivation.level, position.copied());
+        let rivals: Vec<_> = locals
+            .get(&key)
+            .into_iter()
+            .flatten()
+            .filter(|(_, s)| s.is_none() || *s != sect)
+            .collect();
+        let Some((challenger, _)) = rivals.choose(&mut rng) else {
+            continue;
+        };
+        let challenger = data.get(*challenger).unwrap();
+        if !will_battle(&challenger, &holder) {
+            continue;
+        }
+        // A holder who would not fight may get away, keeping the vein for now.
+        if !will_battle(&holder, &challenger)
+            && let Some(ev) = battle::flee(&mut rng, &config, &holder, &challenger)
+        {
+            ev_escape.write(ev);
+            continue;
+        }
+        pairs.claim(challenger.entity, holder.entity);
+    }
+}
+
+fn transfer_veins(
+    mut commands: Commands,
+    mut ev_battle: EventReader<BattleEvent>,
+    holders: Query<&VeinHolder>,
+    mut veins: Query<(&mut SpiritualVein, Option<&Position>)>,
+) {
+    let mut gained = HashSet::new();
+    for ev in ev_battle.read() {
+        let Ok(holder) = holders.get(ev.loser) else {
+            continue;
+        };
+        let Ok((mut vein, position)) = veins.get_mut(holder.vein) else {
+            continue;
+        };
+        commands.entity(ev.loser).remove::<VeinHolder>();
+        vein.holder = None;
+        if holders.contains(ev.winner) || !gained.insert(ev.winner) {
+            continue;
+        }
+        vein.holder = Some(ev.winner);
+        let mut winner = commands.entity(ev.winner);
+        winner.insert(VeinHolder { vein: holder.vein });
+        if let Some(position) = position {
+            winner.insert(*position);
+        }
+    }
+}
+
+fn release_veins(
+    mut commands: Commands,
+    mut veins: Query<&mut SpiritualVein>,
+    holders: Query<(&Life, Has<Injury>), With<VeinHolder>>,
+) {
+    for mut vein in &mut veins {
+        let Some(holder) = vein.holder else {
+            continue;
+        };
+        match holders.get(holder) {
+            Ok((life, false)) if life.alive => {}
+            Ok((_, true)) => {
+                commands.entity(holder).remove::<VeinHolder>();
+                vein.holder = None;
+            }
+            _ => vein.holder = None,
+        }
+    }
+}
+
+pub fn vein_plugin(app: &mut App) {
+    app.add_systems(Startup, spawn_veins.after(seed_entropy));
+    app.add_systems(
+        Update,
+        (
+            claim_veins.after(region::migrate).in_set(GamePlay::Spawn),
+            challenge_veins
+                .after(sect::take_revenge)
+                .in_set(GamePlay::FilterPair),
+            transfer_veins
+                .after(battle::battle)
+                .in_set(GamePlay::Battle),
+            release_veins
+                .after(Life::increase_age)
+                .before(despawn_dead)
+                .in_set(GamePlay::Finish),
+        ),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battle::Courage;
+    use crate::level::Level;
+    use crate::sect::SectMember;
+    use bevy_rand::plugin::EntropyPlugin;
+
+    #[test]
+    fn test_transfer_veins() {
+        let mut app = App::new();
+        app.add_event::<BattleEvent>();
+        app.add_systems(Update, transfer_veins);
+
+        let vein = app.world_mut().spawn(SpiritualVein { holder: None }).id();
+        let loser = app.world_mut().spawn(VeinHolder { vein }).id();
+        let winner = app.world_mut().spawn_empty().id();
+        app.world_mut()
+            .get_mut::<SpiritualVein>(vein)
+            .unwrap()
+            .holder = Some(loser);
+        app.world_mut().send_event(BattleEvent {
+            winner,
+            loser,
+            killed: true,
+        });
+        app.update();
+
+        assert_eq!(
+            app.world().get::<SpiritualVein>(vein).unwrap().holder,
+            Some(winner)
+        );
+        assert_eq!(app.world().get::<VeinHolder>(winner).unwrap().vein, vein);
+        assert!(app.world().get::<VeinHolder>(loser).is_none());
+    }
+
+    #[test]
+    fn test_challenge_veins_sect() {
+        let mut app = App::new();
+        app.add_plugins(EntropyPlugin::<WyRand>::with_seed(42u64.to_le_bytes()));
+        app.add_event::<EscapeEvent>();
+        app.init_resource::<BattlePair>();
+        let mut config = Config::default();
+        config.vein.challenge_rate = 1.0;
+        app.insert_resource(config);
+        app.add_systems(Update, challenge_veins);
+
+        let sect = app.world_mut().spawn_empty().id();
+        let vein = app.world_mut().spawn(SpiritualVein { holder: None }).id();
+        let mut spawn = || {
+            app.world_mut()
+                .spawn((
+                    Cultivation {
+                        level: Level::Foundation,
+                        cultivation: 10,
+                    },
+                    Courage { courage: 1.0 },
+                    Life {
+                        age: 20,
+                        lifespan: 100,
+                        alive: true,
+                        ticks: 0,
+                    },
+                    SectMember { sect },
+                ))
+                .id()
+        };
+        let (holder, challenger) = (spawn(), spawn());
+        app.world_mut()
+            .entity_mut(holder)
+            .insert(VeinHolder { vein });
+        app.world_mut()
+            .get_mut::<SpiritualVein>(vein)
+            .unwrap()
+            .holder = Some(holder);
+        app.update();
+        assert_eq!(app.world().resource::<BattlePair>().len(), 0);
+
+        app.world_mut()
+            .entity_mut(challenger)
+            .remove::<SectMember>();
+        app.update();
+        assert_eq!(app.world().resource::<BattlePair>().len(), 1);
+    }
+}
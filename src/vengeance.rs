@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use bevy_prng::WyRand;
+use bevy_rand::global::GlobalEntropy;
+use rand::Rng;
+
+use crate::battle::{self, Available, BattleEvent, BattlePair};
+use crate::config::Config;
+use crate::cultivation::Cultivation;
+use crate::demonic;
+use crate::life::Life;
+use crate::lineage::{Lineage, Master};
+use crate::system::GamePlay;
+
+// Killers of a cultivator's master or disciples, faced with `courage` extra courage.
+#[derive(Component, Debug)]
+pub struct Grudge {
+    pub killers: Vec<Entity>,
+    pub courage: f64,
+}
+
+#[derive(Event)]
+pub struct VengeanceEvent {
+    pub avenger: Entity,
+    pub killer: Entity,
+    pub avenged: bool,
+}
+
+fn remember_killers(
+    mut commands: Commands,
+    config: Res<Config>,
+    mut ev_battle: EventReader<BattleEvent>,
+    mut ev_vengeance: EventWriter<VengeanceEvent>,
+    kin: Query<(Option<&Lineage>, Option<&Master>)>,
+    lives: Query<&Life>,
+    mut grudges: Query<&mut Grudge>,
+) {
+    if !config.vengeance.enabled {
+        return;
+    }
+    let mut sworn: BTreeMap<Entity, Vec<Entity>> = BTreeMap::new();
+    for ev in ev_battle.read() {
+        if !ev.killed {
+            continue;
+        }
+        for (avenger, killer, avenged) in
+            [(ev.winner, ev.loser, true), (ev.loser, ev.winner, false)]
+        {
+            if grudges
+                .get(avenger)
+                .is_ok_and(|g| g.killers.contains(&killer))
+            {
+                ev_vengeance.write(VengeanceEvent {
+                    avenger,
+                    killer,
+                    avenged,
+                });
+            }
+        }
+        let Ok((lineage, master)) = kin.get(ev.loser) else {
+            continue;
+        };
+        let elder = lineage.and_then(|l| l.master);
+        let disciples = master.map(|m| m.disciples.as_slice()).unwrap_or_default();
+        for avenger in elder.iter().chain(disciples) {
+            if *avenger != ev.winner && lives.get(*avenger).is_ok_and(|l| l.alive) {
+                sworn.entry(*avenger).or_default().push(ev.winner);
+            }
+        }
+    }
+    for (avenger, killers) in sworn {
+        match grudges.get_mut(avenger) {
+            Ok(mut grudge) => grudge.killers.extend(killers),
+            Err(_) => {
+                commands.entity(avenger).insert(Grudge {
+                    killers,
+                    courage: config.vengeance.courage_boost,
+                });
+            }
+        }
+    }
+}
+
+fn seek_killers(
+    mut rng: GlobalEntropy<WyRand>,
+    config: Res<Config>,
+    mut pairs: ResMut<BattlePair>,
+    avengers: Query<(Entity, &mut Grudge, &Cultivation), Available>,
+    lives: Query<&Life>,
+    targets: Query<&Cultivation, Available>,
+) {
+    let seek_rate = config.time.rate(config.vengeance.seek_rate);
+    for (avenger, mut grudge, cult) in avengers {
+        // Killers out of reach for now stay remembered, only the dead are forgotten.
+        grudge
+            .killers
+            .retain(|k| lives.get(*k).is_ok_and(|l| l.alive));
+        let same_level = grudge
+            .killers
+            .iter()
+            .find(|k| targets.get(**k).is_ok_and(|c| c.level == cult.level));
+        if let Some(killer) = same_level
+            && rng.random::<f64>() < seek_rate
+        {
+            pairs.claim(avenger, *killer);
+        }
+    }
+}
+
+pub fn vengeance_plugin(app: &mut App) {
+    app.add_event::<VengeanceEvent>();
+    app.add_systems(
+        Update,
+        (
+            seek_killers.after(battle::pair).in_set(GamePlay::Pair),
+            remember_killers
+                .after(demonic::convert)
+                .in_set(GamePlay::Battle),
+        ),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::injury::Injury;
+    use crate::level::Level;
+    use bevy_rand::plugin::EntropyPlugin;
+
+    #[test]
+    fn test_remember_killers() {
+        let mut app = App::new();
+        app.add_event::<BattleEvent>();
+        app.add_event::<VengeanceEvent>();
+        let mut config = Config::default();
+        config.vengeance.enabled = true;
+        app.insert_resource(config);
+        app.add_systems(Update, remember_killers);
+
+        let life = Life {
+            age: 50,
+            lifespan: 100,
+            alive: true,
+            ticks: 0,
+        };
+        let master = app.world_mut().spawn(life.clone()).id();
+        let disciple = app.world_mut().spawn(life.clone()).id();
+        let victim = app
+            .world_mut()
+            .spawn((
+                life.clone(),
+                Lineage {
+                    founder: master,
+                    master: Some(master),
+                    generation: 1,
+                },
+                Master {
+                    disciples: vec![disciple],
+                },
+            ))
+            .id();
+        let killer = app
+            .world_mut()
+            .spawn((
+                life,
+                Cultivation {
+                    level: Level::Foundation,
+                    cultivation: 10,
+                },
+            ))
+            .id();
+        app.world_mut().send_event(BattleEvent {
+            winner: killer,
+            loser: victim,
+            killed: true,
+        });
+        app.update();
+
+        assert_eq!(
+            app.world().get::<Grudge>(master).unwrap().killers,
+            vec![killer]
+        );
+        assert_eq!(
+            app.world().get::<Grudge>(disciple).unwrap().killers,
+            vec![killer]
+        );
+    }
+
+    #[test]
+    fn test_seek_injured_killer() {
+        let mut app = App::new();
+        app.add_plugins(EntropyPlugin::<WyRand>::default());
+        app.init_resource::<BattlePair>();
+        let mut config = Config::default();
+        config.vengeance.seek_rate = 1.0;
+        app.insert_resource(config);
+        app.add_systems(Update, seek_killers);
+
+        let cultivator = (
+            Cultivation {
+                level: Level::Foundation,
+                cultivation: 10,
+            },
+            Life {
+                age: 50,
+                lifespan: 100,
+                alive: true,
+                ticks: 0,
+            },
+        );
+        let killer = app
+            .world_mut()
+            .spawn((cultivator.clone(), Injury { ticks: 1 }))
+            .id();
+        let avenger = app
+            .world_mut()
+            .spawn((
+                cultivator,
+                Grudge {
+                    killers: vec![killer],
+                    courage: 0.0,
+                },
+            ))
+            .id();
+
+        // An injured killer cannot be sought, but is not forgotten.
+        app.update();
+        assert_eq!(app.world().resource::<BattlePair>().len(), 0);
+        assert_eq!(
+            app.world().get::<Grudge>(avenger).unwrap().killers,
+            vec![killer]
+        );
+
+        app.world_mut().entity_mut(killer).remove::<Injury>();
+        app.update();
+        assert_eq!(app.world().resource::<BattlePair>().len(), 1);
+    }
+}